@@ -1,13 +1,30 @@
+use serde::{Deserialize, Serialize};
+
 use crate::value::Value;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ValueType {
     Integer,
     Float,
     Bool,
+    String,
+    List,
+}
+
+impl ValueType {
+    /// The type as written in source.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueType::Integer => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+            ValueType::String => "string",
+            ValueType::List => "list",
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ExprOpKind {
     EAdd,
     ESub,
@@ -15,13 +32,35 @@ pub enum ExprOpKind {
     EDiv,
 }
 
-#[derive(Debug, PartialEq)]
+impl ExprOpKind {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ExprOpKind::EAdd => "+",
+            ExprOpKind::ESub => "-",
+            ExprOpKind::EMul => "*",
+            ExprOpKind::EDiv => "/",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOpKind {
     UPlus,
     UMinus,
 }
 
-#[derive(Debug, PartialEq)]
+impl UnaryOpKind {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOpKind::UPlus => "+",
+            UnaryOpKind::UMinus => "-",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AssignmentOpKind {
     AEqual,
     AAdd,
@@ -30,7 +69,20 @@ pub enum AssignmentOpKind {
     ADiv,
 }
 
-#[derive(Debug, PartialEq)]
+impl AssignmentOpKind {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            AssignmentOpKind::AEqual => "=",
+            AssignmentOpKind::AAdd => "+=",
+            AssignmentOpKind::ASub => "-=",
+            AssignmentOpKind::AMul => "*=",
+            AssignmentOpKind::ADiv => "/=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ComparisonOpKind {
     CEqual,
     CNot,
@@ -40,16 +92,64 @@ pub enum ComparisonOpKind {
     CLessEqual,
 }
 
-#[derive(Debug, PartialEq)]
+impl ComparisonOpKind {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ComparisonOpKind::CEqual => "==",
+            ComparisonOpKind::CNot => "!=",
+            ComparisonOpKind::CGreater => ">",
+            ComparisonOpKind::CLess => "<",
+            ComparisonOpKind::CGreaterEqual => ">=",
+            ComparisonOpKind::CLessEqual => "<=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LogicalOpKind {
     LAnd,
     LOr,
 }
 
-#[derive(Debug, PartialEq)]
+impl LogicalOpKind {
+    /// The operator as written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LogicalOpKind::LAnd => "&&",
+            LogicalOpKind::LOr => "||",
+        }
+    }
+}
+
+/// What an `import` names: a file relative to the importing one, or a module found
+/// on the search path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ModulePath {
+    /// `import "path/to/lib.wy";`
+    File(String),
+    /// `import math;`
+    Name(String),
+}
+
+impl ModulePath {
+    /// The name the module is bound to without `as`: the file name without its
+    /// extension, or the module name.
+    pub fn default_alias(&self) -> String {
+        match self {
+            ModulePath::File(path) => std::path::Path::new(path)
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            ModulePath::Name(name) => name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Ast {
     Literal(Value),
 
+    /// A variable, or `module.name` for one exported by an imported module.
     Variable(String),
 
     Expr {
@@ -80,6 +180,9 @@ pub enum Ast {
         name: String,
         value_type: ValueType,
         expr: Box<Ast>,
+        /// Declared with `pub`, so that modules importing this one can read it.
+        #[serde(default)]
+        public: bool,
     },
 
     VariableAssignment {
@@ -88,8 +191,61 @@ pub enum Ast {
         expr: Box<Ast>,
     },
 
+    /// `name` may be `module.name`, like a variable's.
     FunctionCall {
         name: String,
-        argument: Box<Ast>,
+        arguments: Vec<Ast>,
+    },
+
+    /// `import "lib.wy" as name;`; without `as` the alias is
+    /// `ModulePath::default_alias`.
+    Import {
+        module: ModulePath,
+        alias: Option<String>,
+    },
+
+    /// `test "name" { ... }`: skipped by normal runs, run by `whiteye test`.
+    Test {
+        name: String,
+        body: Vec<Ast>,
     },
 }
+
+impl Ast {
+    /// What kind of node this is, for error messages: "literal", "import" and so on.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Ast::Literal(_) => "literal",
+            Ast::Variable(_) => "variable",
+            Ast::Expr { .. } => "arithmetic expression",
+            Ast::Monomial { .. } => "unary expression",
+            Ast::ComparisonExpr { .. } => "comparison",
+            Ast::LogicalExpr { .. } => "logical expression",
+            Ast::VariableDeclaration { .. } => "variable declaration",
+            Ast::VariableAssignment { .. } => "assignment",
+            Ast::FunctionCall { .. } => "function call",
+            Ast::Import { .. } => "import",
+            Ast::Test { .. } => "test block",
+        }
+    }
+}
+
+/// Writes a parsed program as JSON, for tools outside the interpreter.
+///
+/// ```
+/// use whiteye::ast;
+/// use whiteye::parser::parse;
+///
+/// let program = parse("println(1 + 2);").unwrap();
+/// let json = ast::to_json(&program);
+/// assert_eq!(ast::from_json(&json).unwrap(), program);
+/// ```
+pub fn to_json(program: &[Ast]) -> String {
+    serde_json::to_string_pretty(program).expect("ASTs always serialize")
+}
+
+/// Reads a program written by `to_json`, or synthesized by another tool, ready for
+/// `Machine::run`.
+pub fn from_json(json: &str) -> Result<Vec<Ast>, serde_json::Error> {
+    serde_json::from_str(json)
+}
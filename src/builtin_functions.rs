@@ -1,5 +1,128 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::host_function::check_arity;
+use crate::machine::{Machine, MachineError};
+use crate::module::Module;
 use crate::value::Value;
 
-pub fn print(value: Value) {
-    println!("{}", value);
+mod format;
+mod io;
+mod math;
+mod string;
+
+pub use math::CONSTANTS;
+
+/// The built-in functions grouped into modules, which scripts may also import to
+/// call them qualified: `import math as m; println(m.sqrt(2.0));`.
+pub const MODULES: [(&str, &[&str]); 3] = [
+    ("math", &math::FUNCTIONS),
+    ("string", &string::FUNCTIONS),
+    ("io", &io::FUNCTIONS),
+];
+
+pub fn register(machine: &mut Machine) {
+    machine.register_native("print", Rc::new(print));
+    machine.register_native("println", Rc::new(println));
+    machine.register_native("assert", Rc::new(assert));
+    machine.register_native("assert_eq", Rc::new(assert_eq));
+    math::register(machine);
+    io::register(machine);
+    format::register(machine);
+    string::register(machine);
+}
+
+/// The value of the built-in constant `name`, such as `PI`.
+pub fn constant(name: &str) -> Option<Value> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, value)| Value::Float(*value))
+}
+
+/// The built-in module `name`, if there is one. `math` also exports the constants.
+pub fn module(name: &str) -> Option<Module> {
+    let (_, functions) = MODULES.iter().find(|(module, _)| *module == name)?;
+    let exports: BTreeMap<String, Value> = match name {
+        "math" => CONSTANTS
+            .iter()
+            .map(|(name, value)| (name.to_string(), Value::Float(*value)))
+            .collect(),
+        _ => BTreeMap::new(),
+    };
+    Some(Module {
+        path: PathBuf::from(name),
+        exports,
+        functions: functions.iter().map(|name| name.to_string()).collect(),
+    })
+}
+
+/// `print(a, b, ...)`: writes the values separated by spaces, without a newline.
+pub fn print(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    machine
+        .output()
+        .write_all(line(&arguments, "").as_bytes())?;
+    Ok(None)
+}
+
+/// `println(a, b, ...)`: like `print`, then a newline.
+pub fn println(
+    machine: &mut Machine,
+    arguments: Vec<Value>,
+) -> Result<Option<Value>, MachineError> {
+    // One write per call, so that `Machine::set_output_fn` sees whole lines.
+    machine
+        .output()
+        .write_all(line(&arguments, "\n").as_bytes())?;
+    Ok(None)
+}
+
+/// The values separated by spaces and followed by `end`, as printed.
+pub(crate) fn line(values: &[Value], end: &str) -> String {
+    let values: Vec<String> = values.iter().map(Value::to_string).collect();
+    values.join(" ") + end
+}
+
+/// `assert(condition)` or `assert(condition, message)`: fails unless `condition` is
+/// true, reporting `message` if given.
+pub fn assert(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    if arguments.len() != 2 {
+        check_arity("assert", &arguments, 1)?;
+    }
+    match &arguments[0] {
+        Value::Bool(true) => Ok(None),
+        Value::Bool(false) => Err(MachineError::AssertionFailed(match arguments.get(1) {
+            Some(message) => message.to_string(),
+            None => "condition is false".to_string(),
+        })),
+        other => Err(MachineError::ArgumentType {
+            name: "assert".to_string(),
+            position: 1,
+            expected: "bool".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// `assert_eq(left, right)`: fails unless the two values are equal, including in type.
+pub fn assert_eq(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    check_arity("assert_eq", &arguments, 2)?;
+    let mut arguments = arguments.into_iter();
+    let (left, right) = (arguments.next().unwrap(), arguments.next().unwrap());
+    if identical(&left, &right) {
+        Ok(None)
+    } else {
+        Err(MachineError::AssertEqFailed { left, right })
+    }
+}
+
+/// Equal and of the same type, unlike `==`, which takes `1` to equal `1.0`.
+fn identical(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::List(l), Value::List(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| identical(l, r))
+        }
+        (l, r) => l.type_name() == r.type_name() && l == r,
+    }
 }
@@ -0,0 +1,262 @@
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+use crate::machine::{Machine, MachineError};
+use crate::value::Value;
+
+pub fn register(machine: &mut Machine) {
+    machine.register_native("format", Rc::new(format));
+}
+
+/// `format(template, ...)`: `template` with each `{...}` placeholder replaced.
+///
+/// A placeholder is `{}` for the next argument, `{1}` for an argument by position
+/// from 0, or `{name}` for a variable, optionally followed by `:` and a spec of
+/// `[[fill]align][0][width][.precision][radix]`: `align` is `<`, `^` or `>`, `0` pads
+/// numbers with zeros after the sign, `precision` is the digits after the point for
+/// numbers or the characters kept of a string, and `radix` is `x`, `X`, `o` or `b`
+/// for ints. `{{` and `}}` stand for braces.
+pub fn format(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let (template, arguments) = match arguments.split_first() {
+        Some((Value::String(template), arguments)) => (template, arguments),
+        Some((other, _)) => {
+            return Err(MachineError::ArgumentType {
+                name: "format".to_string(),
+                position: 1,
+                expected: "string".to_string(),
+                found: other.type_name().to_string(),
+            })
+        }
+        None => {
+            return Err(MachineError::ArgumentCount {
+                name: "format".to_string(),
+                expected: 1,
+                found: 0,
+            })
+        }
+    };
+    let machine = &*machine;
+    let text = format_values(
+        template,
+        arguments,
+        |name| machine.variable(name.to_string()).ok(),
+        |size| machine.check_size("format", size),
+    )?;
+    Ok(Some(Value::String(text)))
+}
+
+fn failed(message: String) -> MachineError {
+    MachineError::FunctionFailed {
+        name: "format".to_string(),
+        message,
+    }
+}
+
+/// Fills in `template` as `format` does, looking up `{name}` placeholders with `named`.
+/// `check_size` is given each width and precision, and the text so far, so that
+/// none is allocated over the size limit.
+fn format_values(
+    template: &str,
+    arguments: &[Value],
+    named: impl Fn(&str) -> Option<Value>,
+    check_size: impl Fn(usize) -> Result<(), MachineError>,
+) -> Result<String, MachineError> {
+    let mut text = String::new();
+    let mut used = vec![false; arguments.len()];
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => {
+                return Err(failed(
+                    "unmatched `}` in format string; write `}}` for a brace".into(),
+                ))
+            }
+            '{' => {
+                let placeholder = placeholder(&mut chars).map_err(failed)?;
+                let (key, spec) = match placeholder.split_once(':') {
+                    Some((key, spec)) => (key, spec),
+                    None => (placeholder.as_str(), ""),
+                };
+                let value = if key.is_empty() || key.chars().all(|c| c.is_ascii_digit()) {
+                    let index = match key.parse() {
+                        Ok(index) => index,
+                        Err(_) => {
+                            next += 1;
+                            next - 1
+                        }
+                    };
+                    let value = arguments.get(index).ok_or_else(|| {
+                        failed(format!(
+                            "placeholder {{{}}} needs argument {}, but only {} given",
+                            placeholder,
+                            index,
+                            arguments.len()
+                        ))
+                    })?;
+                    used[index] = true;
+                    value.clone()
+                } else {
+                    named(key).ok_or_else(|| {
+                        failed(format!("no variable `{}` for {{{}}}", key, placeholder))
+                    })?
+                };
+                let spec = Spec::parse(spec).map_err(failed)?;
+                check_size(spec.width)?;
+                check_size(spec.precision.unwrap_or(0))?;
+                text.push_str(&spec.apply(&value).map_err(failed)?);
+                check_size(text.len())?;
+            }
+            c => text.push(c),
+        }
+    }
+    match used.iter().position(|used| !used) {
+        Some(index) => Err(failed(format!("argument {} is never used", index))),
+        None => Ok(text),
+    }
+}
+
+/// The inside of a placeholder, whose `{` has been read.
+fn placeholder(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut placeholder = String::new();
+    for c in chars {
+        if c == '}' {
+            return Ok(placeholder);
+        }
+        placeholder.push(c);
+    }
+    Err("unclosed `{` in format string; write `{{` for a brace".into())
+}
+
+#[derive(Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    radix: Option<char>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid format spec `{}`", spec);
+        let chars: Vec<char> = spec.chars().collect();
+        let mut parsed = Spec::default();
+        let mut i = 0;
+        let is_align = |c: Option<&char>| matches!(c, Some('<') | Some('^') | Some('>'));
+        if is_align(chars.get(1)) {
+            parsed.fill = Some(chars[0]);
+            parsed.align = Some(chars[1]);
+            i = 2;
+        } else if is_align(chars.first()) {
+            parsed.align = Some(chars[0]);
+            i = 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            parsed.zero = true;
+            i += 1;
+        }
+        let digits = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+            chars[start..*i].iter().collect::<String>()
+        };
+        let width = digits(&mut i);
+        if !width.is_empty() {
+            parsed.width = width.parse().map_err(|_| invalid())?;
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            let precision = digits(&mut i);
+            // Rust's own formatting takes no more than `u16::MAX` digits.
+            let precision: u16 = precision.parse().map_err(|_| invalid())?;
+            parsed.precision = Some(precision.into());
+        }
+        if let Some(radix @ ('x' | 'X' | 'o' | 'b')) = chars.get(i) {
+            parsed.radix = Some(*radix);
+            i += 1;
+        }
+        if i < chars.len() {
+            return Err(invalid());
+        }
+        Ok(parsed)
+    }
+
+    fn apply(&self, value: &Value) -> Result<String, String> {
+        let is_number = matches!(value, Value::Integer(_) | Value::Float(_));
+        let text = match (value, self.radix, self.precision) {
+            (Value::Integer(v), Some(radix), _) => {
+                let digits = match radix {
+                    'x' => format!("{:x}", v.unsigned_abs()),
+                    'X' => format!("{:X}", v.unsigned_abs()),
+                    'o' => format!("{:o}", v.unsigned_abs()),
+                    _ => format!("{:b}", v.unsigned_abs()),
+                };
+                if *v < 0 {
+                    format!("-{}", digits)
+                } else {
+                    digits
+                }
+            }
+            (other, Some(radix), _) => {
+                return Err(format!(
+                    "radix `{}` needs an int, found {}",
+                    radix,
+                    other.type_name()
+                ))
+            }
+            // Not through `f64`, which would round ints past 2^53.
+            (Value::Integer(v), None, Some(0)) => v.to_string(),
+            (Value::Integer(v), None, Some(precision)) => {
+                format!("{}.{}", v, "0".repeat(precision))
+            }
+            (Value::Float(v), None, Some(precision)) if v.is_finite() => {
+                format!("{:.*}", precision, v)
+            }
+            (Value::String(s), None, Some(precision)) => s.chars().take(precision).collect(),
+            (Value::Bool(_), None, Some(_)) | (Value::List(_), None, Some(_)) => {
+                return Err(format!(
+                    "precision needs a number or string, found {}",
+                    value.type_name()
+                ))
+            }
+            (value, None, _) => value.to_string(),
+        };
+        Ok(self.pad(text, is_number))
+    }
+
+    fn pad(&self, text: String, is_number: bool) -> String {
+        let length = text.chars().count();
+        if length >= self.width {
+            return text;
+        }
+        let padding = self.width - length;
+        if self.zero && is_number {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return format!("{}{}{}", sign, "0".repeat(padding), digits);
+        }
+        let fill = self.fill.unwrap_or(' ').to_string();
+        let align = self.align.unwrap_or(if is_number { '>' } else { '<' });
+        let (before, after) = match align {
+            '<' => (0, padding),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (padding, 0),
+        };
+        format!("{}{}{}", fill.repeat(before), text, fill.repeat(after))
+    }
+}
@@ -0,0 +1,158 @@
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::host_function::check_arity;
+use crate::machine::{Machine, MachineError};
+use crate::sandbox::Capability;
+use crate::value::Value;
+
+/// The functions of the `io` module, including `print` and `println`.
+pub const FUNCTIONS: [&str; 11] = [
+    "print",
+    "println",
+    "input",
+    "eprint",
+    "eprintln",
+    "args",
+    "read_file",
+    "read_lines",
+    "write_file",
+    "append_file",
+    "exists",
+];
+
+// Failures are reported as `MachineError::FunctionFailed` naming the path, so that
+// hosts can tell them from errors in the script itself.
+pub fn register(machine: &mut Machine) {
+    machine.register_native_with_capability("input", Capability::Console, Rc::new(input));
+    machine.register_native_with_capability("eprint", Capability::Console, Rc::new(eprint));
+    machine.register_native_with_capability("eprintln", Capability::Console, Rc::new(eprintln));
+    machine.register_native_with_capability("args", Capability::Environment, Rc::new(args));
+
+    machine.register_native_with_capability(
+        "read_file",
+        Capability::FileSystem,
+        Rc::new(read_file),
+    );
+    machine.register_native_with_capability(
+        "read_lines",
+        Capability::FileSystem,
+        Rc::new(read_lines),
+    );
+    machine.register_fn_with_capability(
+        "write_file",
+        Capability::FileSystem,
+        |path: String, text: String| fs::write(&path, text).map_err(|e| failure(&path, e)),
+    );
+    machine.register_fn_with_capability(
+        "append_file",
+        Capability::FileSystem,
+        |path: String, text: String| {
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(text.as_bytes()))
+                .map_err(|e| failure(&path, e))
+        },
+    );
+    machine.register_fn_with_capability("exists", Capability::FileSystem, |path: String| {
+        Path::new(&path).exists()
+    });
+}
+
+fn failure(path: &str, error: std::io::Error) -> String {
+    format!("{}: {}", path, error)
+}
+
+/// `input()` or `input(prompt)`: the next line of input, without its line ending.
+/// The prompt is printed first, without a newline.
+pub fn input(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    if arguments.len() != 1 {
+        check_arity("input", &arguments, 0)?;
+    }
+    if let Some(prompt) = arguments.first() {
+        let output = machine.output();
+        write!(output, "{}", prompt)?;
+        output.flush()?;
+    }
+    match machine.read_line()? {
+        Some(line) => Ok(Some(Value::String(line))),
+        None => Err(MachineError::FunctionFailed {
+            name: "input".to_string(),
+            message: "end of input".to_string(),
+        }),
+    }
+}
+
+/// `eprint(a, b, ...)`: like `print`, but to stderr.
+pub fn eprint(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let line = super::line(&arguments, "");
+    machine.error_output().write_all(line.as_bytes())?;
+    Ok(None)
+}
+
+/// `eprintln(a, b, ...)`: like `println`, but to stderr.
+pub fn eprintln(
+    machine: &mut Machine,
+    arguments: Vec<Value>,
+) -> Result<Option<Value>, MachineError> {
+    let line = super::line(&arguments, "\n");
+    machine.error_output().write_all(line.as_bytes())?;
+    Ok(None)
+}
+
+/// `read_file(path)`: the contents of the file at `path`.
+pub fn read_file(
+    machine: &mut Machine,
+    arguments: Vec<Value>,
+) -> Result<Option<Value>, MachineError> {
+    let text = read("read_file", machine, &arguments)?;
+    Ok(Some(Value::String(text)))
+}
+
+/// `read_lines(path)`: the lines of the file at `path`, without their endings.
+pub fn read_lines(
+    machine: &mut Machine,
+    arguments: Vec<Value>,
+) -> Result<Option<Value>, MachineError> {
+    let text = read("read_lines", machine, &arguments)?;
+    let lines: Vec<String> = text.lines().map(String::from).collect();
+    Ok(Some(Value::from(lines)))
+}
+
+/// The text of the file named by the only argument. Its size is checked against
+/// the limit before anything is read, so that a large file is never loaded.
+fn read(name: &str, machine: &Machine, arguments: &[Value]) -> Result<String, MachineError> {
+    check_arity(name, arguments, 1)?;
+    let path = match &arguments[0] {
+        Value::String(path) => path,
+        other => {
+            return Err(MachineError::ArgumentType {
+                name: name.to_string(),
+                position: 1,
+                expected: "string".to_string(),
+                found: other.type_name().to_string(),
+            })
+        }
+    };
+    let failed = |e| MachineError::FunctionFailed {
+        name: name.to_string(),
+        message: failure(path, e),
+    };
+    let mut file = File::open(path).map_err(failed)?;
+    let size = file.metadata().map_err(failed)?.len();
+    machine.check_size(name, usize::try_from(size).unwrap_or(usize::MAX))?;
+    let mut text = String::new();
+    file.read_to_string(&mut text).map_err(failed)?;
+    Ok(text)
+}
+
+/// `args()`: the command-line arguments after the script's path.
+pub fn args(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    check_arity("args", &arguments, 0)?;
+    Ok(Some(Value::from(machine.args().to_vec())))
+}
@@ -0,0 +1,176 @@
+use std::convert::TryInto;
+use std::rc::Rc;
+
+use crate::host_function::check_arity;
+use crate::machine::{Machine, MachineError};
+use crate::value::Value;
+
+/// Names scripts can read like variables, unless they declare their own.
+pub const CONSTANTS: [(&str, f64); 4] = [
+    ("PI", std::f64::consts::PI),
+    ("E", std::f64::consts::E),
+    ("INF", f64::INFINITY),
+    ("NAN", f64::NAN),
+];
+
+/// The functions of the `math` module.
+pub const FUNCTIONS: [&str; 21] = [
+    "abs", "min", "max", "clamp", "pow", "floor", "ceil", "round", "trunc", "sqrt", "exp", "ln",
+    "log10", "sin", "cos", "tan", "asin", "acos", "atan", "atan2", "hypot",
+];
+
+pub fn register(machine: &mut Machine) {
+    machine.register_native("abs", Rc::new(abs));
+    machine.register_native("min", Rc::new(min));
+    machine.register_native("max", Rc::new(max));
+    machine.register_native("clamp", Rc::new(clamp));
+    machine.register_native("pow", Rc::new(pow));
+    machine.register_native("floor", Rc::new(|_, a| round("floor", a, f64::floor)));
+    machine.register_native("ceil", Rc::new(|_, a| round("ceil", a, f64::ceil)));
+    machine.register_native("round", Rc::new(|_, a| round("round", a, f64::round)));
+    machine.register_native("trunc", Rc::new(|_, a| round("trunc", a, f64::trunc)));
+
+    // These always give a float; `f64` arguments also accept integers.
+    machine.register_fn("sqrt", f64::sqrt);
+    machine.register_fn("exp", f64::exp);
+    machine.register_fn("ln", f64::ln);
+    machine.register_fn("log10", f64::log10);
+    machine.register_fn("sin", f64::sin);
+    machine.register_fn("cos", f64::cos);
+    machine.register_fn("tan", f64::tan);
+    machine.register_fn("asin", f64::asin);
+    machine.register_fn("acos", f64::acos);
+    machine.register_fn("atan", f64::atan);
+    machine.register_fn("atan2", f64::atan2);
+    machine.register_fn("hypot", f64::hypot);
+}
+
+/// An argument of a function taking either kind of number.
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(isize),
+    Float(f64),
+}
+
+impl Number {
+    fn to_float(self) -> f64 {
+        match self {
+            Number::Integer(v) => v as f64,
+            Number::Float(v) => v,
+        }
+    }
+}
+
+/// The arguments as numbers, checking that there are `expected` of them.
+fn numbers(name: &str, arguments: &[Value], expected: usize) -> Result<Vec<Number>, MachineError> {
+    check_arity(name, arguments, expected)?;
+    arguments
+        .iter()
+        .enumerate()
+        .map(|(i, argument)| match argument {
+            Value::Integer(v) => Ok(Number::Integer(*v)),
+            Value::Float(v) => Ok(Number::Float(*v)),
+            other => Err(MachineError::ArgumentType {
+                name: name.to_string(),
+                position: i + 1,
+                expected: "number".to_string(),
+                found: other.type_name().to_string(),
+            }),
+        })
+        .collect()
+}
+
+/// The integers, if every argument is one; otherwise all of them as floats.
+fn common_type(numbers: &[Number]) -> Result<Vec<isize>, Vec<f64>> {
+    numbers
+        .iter()
+        .map(|n| match n {
+            Number::Integer(v) => Some(*v),
+            Number::Float(_) => None,
+        })
+        .collect::<Option<Vec<isize>>>()
+        .ok_or_else(|| numbers.iter().map(|n| n.to_float()).collect())
+}
+
+fn failed(name: &str, message: &str) -> MachineError {
+    MachineError::FunctionFailed {
+        name: name.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// `abs(x)`: of the same type as `x`.
+pub fn abs(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let value = match numbers("abs", &arguments, 1)?[0] {
+        Number::Integer(v) => {
+            Value::Integer(v.checked_abs().ok_or_else(|| failed("abs", "overflow"))?)
+        }
+        Number::Float(v) => Value::Float(v.abs()),
+    };
+    Ok(Some(value))
+}
+
+/// `min(a, b)`: an int if both are, otherwise a float.
+pub fn min(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let value = match common_type(&numbers("min", &arguments, 2)?) {
+        Ok(v) => Value::Integer(v[0].min(v[1])),
+        Err(v) => Value::Float(v[0].min(v[1])),
+    };
+    Ok(Some(value))
+}
+
+/// `max(a, b)`: an int if both are, otherwise a float.
+pub fn max(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let value = match common_type(&numbers("max", &arguments, 2)?) {
+        Ok(v) => Value::Integer(v[0].max(v[1])),
+        Err(v) => Value::Float(v[0].max(v[1])),
+    };
+    Ok(Some(value))
+}
+
+/// `clamp(x, low, high)`: `x` limited to `low..=high`; an int if all three are.
+pub fn clamp(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let value = match common_type(&numbers("clamp", &arguments, 3)?) {
+        Ok(v) if v[1] <= v[2] => Value::Integer(v[0].clamp(v[1], v[2])),
+        Err(v) if v[1] <= v[2] => Value::Float(v[0].clamp(v[1], v[2])),
+        _ => return Err(failed("clamp", "low bound is above high bound")),
+    };
+    Ok(Some(value))
+}
+
+/// `pow(base, exponent)`: an int for an int raised to a non-negative int, otherwise
+/// a float.
+pub fn pow(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    let value = match common_type(&numbers("pow", &arguments, 2)?) {
+        Ok(v) if v[1] >= 0 => {
+            let power = v[1]
+                .try_into()
+                .ok()
+                .and_then(|exponent| v[0].checked_pow(exponent));
+            Value::Integer(power.ok_or_else(|| failed("pow", "overflow"))?)
+        }
+        Ok(v) => Value::Float((v[0] as f64).powf(v[1] as f64)),
+        Err(v) => Value::Float(v[0].powf(v[1])),
+    };
+    Ok(Some(value))
+}
+
+/// `floor`, `ceil`, `round` and `trunc`: the int `rounding` gives for `x`.
+fn round(
+    name: &str,
+    arguments: Vec<Value>,
+    rounding: fn(f64) -> f64,
+) -> Result<Option<Value>, MachineError> {
+    let value = match numbers(name, &arguments, 1)?[0] {
+        Number::Integer(v) => v,
+        Number::Float(v) => {
+            let rounded = rounding(v);
+            // `rounded` is whole, so within this range the cast is exact.
+            if !(rounded >= isize::MIN as f64 && rounded < -(isize::MIN as f64)) {
+                return Err(failed(name, &format!("{} is out of the int range", v)));
+            }
+            rounded as isize
+        }
+    };
+    Ok(Some(Value::Integer(value)))
+}
@@ -0,0 +1,208 @@
+use std::rc::Rc;
+
+use crate::host_function::check_arity;
+use crate::lexer::quote;
+use crate::machine::{Machine, MachineError};
+use crate::value::{FromValue, Value};
+
+/// The functions of the `string` module, including `format`.
+pub const FUNCTIONS: [&str; 17] = [
+    "len",
+    "upper",
+    "lower",
+    "trim",
+    "split",
+    "join",
+    "replace",
+    "find",
+    "starts_with",
+    "ends_with",
+    "repeat",
+    "chars",
+    "substring",
+    "parse_int",
+    "parse_float",
+    "to_string",
+    "format",
+];
+
+// Positions and lengths count characters, not bytes, so that they mean the same for
+// Japanese text as for ASCII. Functions that can build a string much larger than
+// their arguments check its size against the machine's limit before building it.
+pub fn register(machine: &mut Machine) {
+    machine.register_native("len", Rc::new(len));
+    machine.register_fn("upper", |s: String| s.to_uppercase());
+    machine.register_fn("lower", |s: String| s.to_lowercase());
+    machine.register_fn("trim", |s: String| s.trim().to_string());
+    machine.register_fn("split", split);
+    machine.register_native("join", Rc::new(join));
+    machine.register_native("replace", Rc::new(replace));
+    machine.register_fn("find", find);
+    machine.register_fn("starts_with", |s: String, prefix: String| {
+        s.starts_with(&prefix)
+    });
+    machine.register_fn("ends_with", |s: String, suffix: String| {
+        s.ends_with(&suffix)
+    });
+    machine.register_native("repeat", Rc::new(repeat));
+    machine.register_fn("chars", |s: String| {
+        s.chars().map(String::from).collect::<Vec<String>>()
+    });
+    machine.register_native("substring", Rc::new(substring));
+    machine.register_native(
+        "parse_int",
+        Rc::new(|_, arguments| parse("parse_int", arguments)),
+    );
+    machine.register_native(
+        "parse_float",
+        Rc::new(|_, arguments| parse("parse_float", arguments)),
+    );
+    machine.register_fn("to_string", |value: Value| value.to_string());
+}
+
+fn failed(name: &str, message: String) -> MachineError {
+    MachineError::FunctionFailed {
+        name: name.to_string(),
+        message,
+    }
+}
+
+fn argument<T: FromValue>(name: &str, position: usize, value: &Value) -> Result<T, MachineError> {
+    T::from_value(value.clone()).ok_or_else(|| MachineError::ArgumentType {
+        name: name.to_string(),
+        position,
+        expected: T::type_name(),
+        found: value.type_name().to_string(),
+    })
+}
+
+/// `len(x)`: the number of characters in a string or of items in a list.
+pub fn len(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    check_arity("len", &arguments, 1)?;
+    let length = match &arguments[0] {
+        Value::String(s) => s.chars().count(),
+        Value::List(items) => items.len(),
+        other => {
+            return Err(MachineError::ArgumentType {
+                name: "len".to_string(),
+                position: 1,
+                expected: "string or list".to_string(),
+                found: other.type_name().to_string(),
+            })
+        }
+    };
+    Ok(Some(Value::Integer(length as isize)))
+}
+
+fn split(s: String, separator: String) -> Result<Vec<String>, &'static str> {
+    if separator.is_empty() {
+        return Err("empty separator; use chars to split into characters");
+    }
+    Ok(s.split(&separator).map(String::from).collect())
+}
+
+/// `join(items, separator)`: the items, of any type, written as `print` would.
+pub fn join(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    check_arity("join", &arguments, 2)?;
+    let items: Vec<Value> = argument("join", 1, &arguments[0])?;
+    let separator: String = argument("join", 2, &arguments[1])?;
+    let items: Vec<String> = items.iter().map(Value::to_string).collect();
+    let size = items.iter().map(String::len).fold(
+        separator
+            .len()
+            .saturating_mul(items.len().saturating_sub(1)),
+        usize::saturating_add,
+    );
+    machine.check_size("join", size)?;
+    Ok(Some(Value::String(items.join(&separator))))
+}
+
+/// `replace(s, from, to)`: `s` with every `from` replaced by `to`.
+pub fn replace(
+    machine: &mut Machine,
+    arguments: Vec<Value>,
+) -> Result<Option<Value>, MachineError> {
+    check_arity("replace", &arguments, 3)?;
+    let s: String = argument("replace", 1, &arguments[0])?;
+    let from: String = argument("replace", 2, &arguments[1])?;
+    let to: String = argument("replace", 3, &arguments[2])?;
+    let count = s.matches(&from).count();
+    let size = (s.len() - count * from.len()).saturating_add(count.saturating_mul(to.len()));
+    machine.check_size("replace", size)?;
+    Ok(Some(Value::String(s.replace(&from, &to))))
+}
+
+/// `find(s, needle)`: the position of the first `needle` in `s`, or -1.
+fn find(s: String, needle: String) -> isize {
+    match s.find(&needle) {
+        Some(byte) => s[..byte].chars().count() as isize,
+        None => -1,
+    }
+}
+
+/// `repeat(s, count)`: `count` copies of `s`.
+pub fn repeat(machine: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    check_arity("repeat", &arguments, 2)?;
+    let s: String = argument("repeat", 1, &arguments[0])?;
+    let count: isize = argument("repeat", 2, &arguments[1])?;
+    if count < 0 {
+        return Err(failed("repeat", format!("negative count {}", count)));
+    }
+    let size = s
+        .len()
+        .checked_mul(count as usize)
+        .ok_or_else(|| failed("repeat", "overflow".to_string()))?;
+    machine.check_size("repeat", size)?;
+    Ok(Some(Value::String(s.repeat(count as usize))))
+}
+
+/// `substring(s, start)` or `substring(s, start, end)`: the characters from `start`
+/// up to, not including, `end` or the end of `s`.
+pub fn substring(_: &mut Machine, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    if arguments.len() != 2 {
+        check_arity("substring", &arguments, 3)?;
+    }
+    let s: String = argument("substring", 1, &arguments[0])?;
+    let length = s.chars().count() as isize;
+    let start: isize = argument("substring", 2, &arguments[1])?;
+    let end: isize = match arguments.get(2) {
+        Some(end) => argument("substring", 3, end)?,
+        None => length,
+    };
+    if start < 0 || start > end || end > length {
+        let message = format!(
+            "range {}..{} is out of bounds for length {}",
+            start, end, length
+        );
+        return Err(failed("substring", message));
+    }
+    let text = s.chars().skip(start as usize).take((end - start) as usize);
+    Ok(Some(Value::String(text.collect())))
+}
+
+/// `parse_int(s)`, `parse_float(s)`: the number written in `s`, ignoring surrounding
+/// whitespace. With a second argument, that is returned instead of failing when `s`
+/// is not a number.
+fn parse(name: &str, arguments: Vec<Value>) -> Result<Option<Value>, MachineError> {
+    if arguments.len() != 2 {
+        check_arity(name, &arguments, 1)?;
+    }
+    let s: String = argument(name, 1, &arguments[0])?;
+    let parsed = if name == "parse_int" {
+        s.trim().parse().map(Value::Integer).ok()
+    } else {
+        s.trim().parse().map(Value::Float).ok()
+    };
+    match (parsed, arguments.get(1)) {
+        (Some(value), _) => Ok(Some(value)),
+        (None, Some(default)) => Ok(Some(default.clone())),
+        (None, None) => {
+            let kind = if name == "parse_int" {
+                "an int"
+            } else {
+                "a float"
+            };
+            Err(failed(name, format!("{} is not {}", quote(&s), kind)))
+        }
+    }
+}
@@ -0,0 +1,196 @@
+pub mod dap;
+
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+use crate::ast::Ast;
+use crate::machine::{DebugHook, Machine};
+use crate::parser::parse_with_tail_expression;
+use crate::sandbox::Progress;
+use crate::value::Value;
+
+/// When to stop next, shared by the debugger front ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stepping {
+    /// Stop at the next statement, entering calls.
+    Into,
+    /// Stop at the next statement no deeper in calls than the given depth.
+    Over(usize),
+    /// Stop at the next statement shallower than the given depth.
+    Out(usize),
+    /// Stop only at breakpoints.
+    Continue,
+}
+
+impl Stepping {
+    pub fn should_stop(self, line: usize, depth: usize, breakpoints: &BTreeSet<usize>) -> bool {
+        breakpoints.contains(&line)
+            || match self {
+                Stepping::Into => true,
+                Stepping::Over(from) => depth <= from,
+                Stepping::Out(from) => depth < from,
+                Stepping::Continue => false,
+            }
+    }
+}
+
+/// Evaluates `expression` against the machine's current variables.
+pub fn evaluate(machine: &mut Machine, expression: &str) -> Result<Value, String> {
+    let tail = match parse_with_tail_expression(expression) {
+        Ok((statements, Some(tail))) if statements.is_empty() => tail,
+        Ok(_) => return Err("expected an expression".to_string()),
+        Err(errors) => return Err(errors[0].to_string()),
+    };
+    machine.eval_expression(tail).map_err(|e| e.to_string())
+}
+
+/// Every variable with its value, sorted by name.
+pub fn variables(machine: &Machine) -> Vec<(String, Value)> {
+    let mut variables: Vec<(String, Value)> = machine
+        .variables
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    variables
+}
+
+const HELP: &str = "\
+break N, b N      stop at line N
+delete N, d N     remove the breakpoint at line N
+step, s           run to the next statement, entering calls
+next, n           run to the next statement, stepping over calls
+finish, out       run until the current call returns
+continue, c       run to the next breakpoint
+print EXPR, p     evaluate EXPR
+vars              show all variables
+list, l           show the source around the current line
+quit, q           stop the program";
+
+/// Interactive command-line debugger, installed with `Machine::set_debug_hook`.
+///
+/// It stops before the first statement and whenever a breakpoint or step lands,
+/// then reads commands from `input` until one resumes the program. The program
+/// must be run with `Machine::run_at` so that statements have locations.
+pub struct Debugger<R, W> {
+    source: String,
+    input: R,
+    output: W,
+    breakpoints: BTreeSet<usize>,
+    stepping: Stepping,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        Debugger {
+            source: source.to_string(),
+            input,
+            output,
+            breakpoints: BTreeSet::new(),
+            stepping: Stepping::Into,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        self.source.lines().nth(line - 1).unwrap_or("")
+    }
+
+    /// Reads and runs commands until one resumes or stops the program.
+    fn prompt(&mut self, machine: &mut Machine, line: usize) -> Progress {
+        loop {
+            let _ = write!(self.output, "(wdb) ");
+            let _ = self.output.flush();
+            let mut command = String::new();
+            match self.input.read_line(&mut command) {
+                Ok(0) | Err(_) => return Progress::Abort,
+                Ok(_) => {}
+            }
+            let command = command.trim();
+            let (name, argument) = match command.find(' ') {
+                Some(i) => (&command[..i], command[i + 1..].trim()),
+                None => (command, ""),
+            };
+            let depth = machine.call_depth();
+            let message = match name {
+                "" => continue,
+                "step" | "s" => return self.resume(Stepping::Into),
+                "next" | "n" => return self.resume(Stepping::Over(depth)),
+                "finish" | "out" => return self.resume(Stepping::Out(depth)),
+                "continue" | "c" => return self.resume(Stepping::Continue),
+                "quit" | "q" => return Progress::Abort,
+                "break" | "b" => match argument.parse() {
+                    Ok(line) if line > 0 => {
+                        self.breakpoints.insert(line);
+                        format!("breakpoint at line {}", line)
+                    }
+                    _ => "usage: break LINE".to_string(),
+                },
+                "delete" | "d" => match argument.parse() {
+                    Ok(line) if self.breakpoints.remove(&line) => {
+                        format!("deleted breakpoint at line {}", line)
+                    }
+                    _ => format!("no breakpoint at line {}", argument),
+                },
+                "print" | "p" => match evaluate(machine, argument) {
+                    Ok(value) => value.to_string(),
+                    Err(e) => format!("error: {}", e),
+                },
+                "vars" => variables(machine)
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                "list" | "l" => {
+                    let first = line.saturating_sub(3).max(1);
+                    (first..line + 3)
+                        .filter(|n| *n <= self.source.lines().count())
+                        .map(|n| {
+                            let marker = if n == line { "->" } else { "  " };
+                            format!("{} {:>4} {}", marker, n, self.source_line(n))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+                "help" | "h" => HELP.to_string(),
+                _ => format!("unknown command `{}`; try `help`", name),
+            };
+            if !message.is_empty() {
+                let _ = writeln!(self.output, "{}", message);
+            }
+        }
+    }
+
+    fn resume(&mut self, stepping: Stepping) -> Progress {
+        self.stepping = stepping;
+        Progress::Continue
+    }
+}
+
+impl<R: BufRead, W: Write> DebugHook for Debugger<R, W> {
+    fn before_statement(&mut self, machine: &mut Machine, _: &Ast) -> Progress {
+        let line = match machine.location() {
+            // Lines of imported modules are in other files, so they are run through.
+            Some(_) if machine.in_module() => return Progress::Continue,
+            Some(span) => span.line,
+            None => return Progress::Continue,
+        };
+        if !self
+            .stepping
+            .should_stop(line, machine.call_depth(), &self.breakpoints)
+        {
+            return Progress::Continue;
+        }
+        let reason = if self.breakpoints.contains(&line) {
+            "breakpoint"
+        } else {
+            "step"
+        };
+        let text = self.source_line(line).trim().to_string();
+        let _ = writeln!(self.output, "{} at line {}: {}", reason, line, text);
+        self.prompt(machine, line)
+    }
+}
@@ -0,0 +1,364 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use serde_json::{json, Value as Json};
+
+use super::{evaluate, variables, Stepping};
+use crate::ast::Ast;
+use crate::lsp::{read_message, write_message};
+use crate::machine::{DebugHook, Machine, MachineError};
+use crate::module::module_path_from_env;
+use crate::parser::parse_with_spans;
+use crate::sandbox::Progress;
+
+/// Scripts are single-threaded; this is the one thread reported to the client.
+const THREAD_ID: u64 = 1;
+/// `variablesReference` of the only scope, which holds every variable.
+const GLOBALS_REFERENCE: u64 = 1;
+
+/// Serves the Debug Adapter Protocol over `input` and `output`.
+///
+/// The client configures the session with `initialize`, `launch` (whose `program`
+/// argument names the script), `setBreakpoints` and `configurationDone`, after which
+/// the script runs. While it is stopped the adapter answers `threads`, `stackTrace`,
+/// `scopes`, `variables` and `evaluate`, and resumes on `continue`, `next`, `stepIn`
+/// or `stepOut`. What the script prints is sent as `output` events.
+pub fn run(input: impl BufRead + 'static, output: impl Write + 'static) -> io::Result<()> {
+    let connection = Rc::new(Connection {
+        input: RefCell::new(Box::new(input)),
+        output: RefCell::new(Box::new(output)),
+        seq: Cell::new(1),
+    });
+    let mut session = Session {
+        connection: connection.clone(),
+        program: String::new(),
+        breakpoints: BTreeSet::new(),
+        stepping: Stepping::Continue,
+        stopped_before: false,
+        disconnected: false,
+    };
+
+    // Configuration: everything up to `configurationDone`.
+    loop {
+        let request = match connection.receive()? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        match command(&request) {
+            "initialize" => {
+                connection.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                )?;
+                connection.event("initialized", Json::Null)?;
+            }
+            "launch" => {
+                let arguments = &request["arguments"];
+                session.program = arguments["program"].as_str().unwrap_or("").to_string();
+                if arguments["stopOnEntry"].as_bool() == Some(true) {
+                    session.stepping = Stepping::Into;
+                }
+                connection.respond(&request, Json::Null)?;
+            }
+            "configurationDone" => {
+                connection.respond(&request, Json::Null)?;
+                break;
+            }
+            _ => {
+                if !session.handle(&request, None)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    let program = fs::read_to_string(&session.program)
+        .map_err(|e| format!("cannot read {}: {}", session.program, e))
+        .and_then(|source| {
+            parse_with_spans(&source).map_err(|errors| {
+                errors
+                    .iter()
+                    .map(|e| e.render(&source))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        });
+    let mut exit_code = 0;
+    match program {
+        Ok(program) => {
+            let mut machine = Machine::new();
+            machine.set_script_path(&session.program);
+            machine.set_module_path(module_path_from_env());
+            let sink = connection.clone();
+            machine.set_output_fn(move |text| {
+                let _ = sink.output_event("stdout", text);
+            });
+            let session = Rc::new(RefCell::new(session));
+            machine.set_debug_hook(Hook(session.clone()));
+            for (statement, span) in program {
+                match machine.run_at(statement, span) {
+                    Ok(()) => {}
+                    Err(MachineError::Aborted) => break,
+                    Err(e) => {
+                        connection.output_event("stderr", &format!("{}\n", e))?;
+                        exit_code = 1;
+                        break;
+                    }
+                }
+            }
+            if session.borrow().disconnected {
+                return Ok(());
+            }
+        }
+        Err(message) => {
+            connection.output_event("stderr", &format!("{}\n", message))?;
+            exit_code = 1;
+        }
+    }
+    connection.event("exited", json!({ "exitCode": exit_code }))?;
+    connection.event("terminated", Json::Null)?;
+
+    while let Some(request) = connection.receive()? {
+        if command(&request) == "disconnect" {
+            return connection.respond(&request, Json::Null);
+        }
+        connection.respond(&request, Json::Null)?;
+    }
+    Ok(())
+}
+
+fn command(request: &Json) -> &str {
+    request["command"].as_str().unwrap_or("")
+}
+
+/// The client's stream, shared by the driver, the debug hook and the machine's output.
+/// Each call borrows only for the message it reads or writes, so output printed by an
+/// `evaluate` request can be sent while that request is being answered.
+struct Connection {
+    input: RefCell<Box<dyn BufRead>>,
+    output: RefCell<Box<dyn Write>>,
+    seq: Cell<u64>,
+}
+
+impl Connection {
+    /// Reads the next request, skipping anything that is not JSON.
+    fn receive(&self) -> io::Result<Option<Json>> {
+        loop {
+            let body = read_message(&mut *self.input.borrow_mut())?;
+            match body.map(|body| serde_json::from_str(&body)) {
+                None => return Ok(None),
+                Some(Ok(request)) => return Ok(Some(request)),
+                Some(Err(_)) => continue,
+            }
+        }
+    }
+
+    fn send(&self, mut message: Json) -> io::Result<()> {
+        message["seq"] = json!(self.seq.get());
+        self.seq.set(self.seq.get() + 1);
+        write_message(&mut *self.output.borrow_mut(), &message)
+    }
+
+    fn respond(&self, request: &Json, body: Json) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&self, request: &Json, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn output_event(&self, category: &str, output: &str) -> io::Result<()> {
+        self.event("output", json!({ "category": category, "output": output }))
+    }
+}
+
+struct Session {
+    connection: Rc<Connection>,
+    program: String,
+    breakpoints: BTreeSet<usize>,
+    stepping: Stepping,
+    /// Whether the script has stopped yet, which tells an entry stop from a step.
+    stopped_before: bool,
+    disconnected: bool,
+}
+
+impl Session {
+    /// Answers a request that is valid both before the run and while stopped.
+    /// `machine` is `None` before the run. Returns `false` on `disconnect`.
+    fn handle(&mut self, request: &Json, machine: Option<&mut Machine>) -> io::Result<bool> {
+        let connection = self.connection.clone();
+        let arguments = &request["arguments"];
+        match (command(request), machine) {
+            ("disconnect", _) => {
+                self.disconnected = true;
+                connection.respond(request, Json::Null)?;
+                return Ok(false);
+            }
+            ("setBreakpoints", _) => {
+                self.breakpoints = arguments["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .map(|line| line as usize)
+                    .collect();
+                let breakpoints: Vec<Json> = self
+                    .breakpoints
+                    .iter()
+                    .map(|line| json!({ "verified": true, "line": line }))
+                    .collect();
+                connection.respond(request, json!({ "breakpoints": breakpoints }))?;
+            }
+            ("threads", _) => connection.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            )?,
+            ("stackTrace", Some(machine)) => {
+                let span = machine.location().unwrap_or_default();
+                connection.respond(
+                    request,
+                    json!({
+                        "stackFrames": [{
+                            "id": 0,
+                            "name": "main",
+                            "line": span.line,
+                            "column": span.column,
+                            "source": { "path": self.program },
+                        }],
+                        "totalFrames": 1,
+                    }),
+                )?;
+            }
+            ("scopes", Some(_)) => connection.respond(
+                request,
+                json!({ "scopes": [{
+                    "name": "Globals",
+                    "variablesReference": GLOBALS_REFERENCE,
+                    "expensive": false,
+                }] }),
+            )?,
+            ("variables", Some(machine)) => {
+                let variables: Vec<Json> = variables(machine)
+                    .into_iter()
+                    .map(|(name, value)| {
+                        json!({
+                            "name": name,
+                            "value": value.to_string(),
+                            "type": value.type_name(),
+                            "variablesReference": 0,
+                        })
+                    })
+                    .collect();
+                connection.respond(request, json!({ "variables": variables }))?;
+            }
+            ("evaluate", Some(machine)) => {
+                let expression = arguments["expression"].as_str().unwrap_or("");
+                match evaluate(machine, expression) {
+                    Ok(value) => connection.respond(
+                        request,
+                        json!({
+                            "result": value.to_string(),
+                            "type": value.type_name(),
+                            "variablesReference": 0,
+                        }),
+                    )?,
+                    Err(message) => connection.fail(request, &message)?,
+                }
+            }
+            ("stackTrace", None) | ("scopes", None) | ("variables", None) | ("evaluate", None) => {
+                connection.fail(request, "the program is not running")?
+            }
+            // Requests this adapter has nothing to say about, such as
+            // `setExceptionBreakpoints`, succeed with an empty body.
+            _ => connection.respond(request, Json::Null)?,
+        }
+        Ok(true)
+    }
+
+    /// Sends `stopped` and serves requests until the client resumes or disconnects.
+    fn stop(&mut self, machine: &mut Machine, line: usize) -> io::Result<Progress> {
+        let reason = if self.breakpoints.contains(&line) {
+            "breakpoint"
+        } else if !self.stopped_before {
+            "entry"
+        } else {
+            "step"
+        };
+        self.stopped_before = true;
+        self.connection.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )?;
+        let depth = machine.call_depth();
+        loop {
+            let request = match self.connection.receive()? {
+                Some(request) => request,
+                None => return Ok(Progress::Abort),
+            };
+            let stepping = match command(&request) {
+                "continue" => Stepping::Continue,
+                "next" => Stepping::Over(depth),
+                "stepIn" => Stepping::Into,
+                "stepOut" => Stepping::Out(depth),
+                _ => {
+                    if !self.handle(&request, Some(machine))? {
+                        return Ok(Progress::Abort);
+                    }
+                    continue;
+                }
+            };
+            let body = if stepping == Stepping::Continue {
+                json!({ "allThreadsContinued": true })
+            } else {
+                Json::Null
+            };
+            self.connection.respond(&request, body)?;
+            self.stepping = stepping;
+            return Ok(Progress::Continue);
+        }
+    }
+}
+
+struct Hook(Rc<RefCell<Session>>);
+
+impl DebugHook for Hook {
+    fn before_statement(&mut self, machine: &mut Machine, _: &Ast) -> Progress {
+        let mut session = self.0.borrow_mut();
+        let line = match machine.location() {
+            // Lines of imported modules are in other files, so they are run through.
+            Some(_) if machine.in_module() => return Progress::Continue,
+            Some(span) => span.line,
+            None => return Progress::Continue,
+        };
+        if !session
+            .stepping
+            .should_stop(line, machine.call_depth(), &session.breakpoints)
+        {
+            return Progress::Continue;
+        }
+        // A client that has gone away cannot resume the program.
+        session.stop(machine, line).unwrap_or(Progress::Abort)
+    }
+}
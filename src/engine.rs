@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::host_function::HostFunction;
+use crate::machine::Machine;
+use crate::parser::{parse, parse_with_tail_expression};
+use crate::snapshot::Snapshot;
+use crate::value::{IntoValue, Value};
+
+/// High-level entry point for running Whiteye code from Rust.
+///
+/// Variables persist between calls, so an `Engine` can be fed a script piece by piece.
+/// Step and time limits set on the machine apply to each call separately.
+///
+/// ```
+/// use whiteye::{Engine, Value};
+///
+/// let mut engine = Engine::new();
+/// engine.set_var("base", 40);
+/// engine.run("let answer: int = base + 2;").unwrap();
+/// assert_eq!(engine.get_var("answer"), Some(&Value::Integer(42)));
+/// assert_eq!(engine.eval("answer * 2").unwrap(), Value::Integer(84));
+/// ```
+#[derive(Debug, Default)]
+pub struct Engine {
+    machine: Machine,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps an existing, possibly pre-configured, machine.
+    pub fn with_machine(machine: Machine) -> Self {
+        Engine { machine }
+    }
+
+    /// Runs `source` and returns the value of the expression it ends with.
+    ///
+    /// The final expression takes no `;`. Source without one is rejected with
+    /// `Error::NoValue` after its statements have run.
+    ///
+    /// ```
+    /// use whiteye::{Engine, Error, Value};
+    ///
+    /// let mut engine = Engine::new();
+    /// assert_eq!(engine.eval("1 + 2 * 3").unwrap(), Value::Integer(7));
+    /// assert_eq!(engine.eval("let x: float = 0.5; x < 1").unwrap(), Value::Bool(true));
+    /// assert!(matches!(engine.eval("let y: int = 1;"), Err(Error::NoValue)));
+    /// assert!(matches!(engine.eval("1 +"), Err(Error::Parse(_))));
+    /// ```
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let (statements, tail) = parse_with_tail_expression(source)?;
+        self.machine.reset_usage();
+        for statement in statements {
+            self.machine.run(statement)?;
+        }
+        match tail {
+            Some(expr) => Ok(self.machine.eval_expression(expr)?),
+            None => Err(Error::NoValue),
+        }
+    }
+
+    /// Runs every statement in `source`.
+    pub fn run(&mut self, source: &str) -> Result<(), Error> {
+        let statements = parse(source)?;
+        self.machine.reset_usage();
+        for statement in statements {
+            self.machine.run(statement)?;
+        }
+        Ok(())
+    }
+
+    /// Reads and runs a script file. Its `import`s are resolved relative to it, as are
+    /// those of later `run` and `eval` calls, until another file is run.
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        self.machine.set_script_path(path);
+        self.run(&source)
+    }
+
+    /// Defines or overwrites a variable visible to scripts.
+    pub fn set_var(&mut self, name: &str, value: impl IntoValue) {
+        self.machine
+            .variables
+            .insert(name.to_string(), value.into_value());
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<&Value> {
+        self.machine.variables.get(name)
+    }
+
+    /// See `Machine::register_fn`.
+    ///
+    /// ```
+    /// use whiteye::{Engine, Value};
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.register_fn("square", |x: f64| x * x);
+    /// assert_eq!(engine.eval("square(3)").unwrap(), Value::Float(9.0));
+    /// ```
+    pub fn register_fn<Args, F: HostFunction<Args>>(&mut self, name: &str, function: F) {
+        self.machine.register_fn(name, function);
+    }
+
+    /// See `Machine::snapshot`.
+    pub fn snapshot(&self) -> Snapshot {
+        self.machine.snapshot()
+    }
+
+    /// Restores variables from a snapshot read with `Snapshot::from_json`.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), Error> {
+        Ok(self.machine.restore(snapshot)?)
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+use std::io;
+
+use crate::machine::MachineError;
+use crate::parser::ParseError;
+use crate::snapshot::SnapshotError;
+
+/// Any error raised while loading, parsing or running a script.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{}", ParseErrors(.0))]
+    Parse(Vec<ParseError>),
+    #[error(transparent)]
+    Runtime(#[from] MachineError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error("source does not end with an expression")]
+    NoValue,
+}
+
+impl From<Vec<ParseError>> for Error {
+    fn from(errors: Vec<ParseError>) -> Self {
+        Error::Parse(errors)
+    }
+}
+
+struct ParseErrors<'a>(&'a [ParseError]);
+
+impl fmt::Display for ParseErrors<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, e) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", e)?;
+        }
+        Ok(())
+    }
+}
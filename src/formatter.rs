@@ -0,0 +1,297 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::ast::{Ast, ExprOpKind, ModulePath};
+use crate::lexer::{quote, tokenize, Comment, Token};
+use crate::parser::{parse_tokens, statement_tokens, test_body_tokens, ParseError};
+use crate::value::Value;
+
+const INDENT: &str = "    ";
+
+/// Reformats a whole source file into the canonical layout: one statement per line,
+/// single spaces around binary operators and only the parentheses precedence needs.
+///
+/// Comments are kept. Those on their own lines stay above the statement that follows
+/// them, those after a statement's `;` stay on its line, and those inside a statement
+/// are moved above it. Runs of blank lines are reduced to one.
+///
+/// ```
+/// use whiteye::formatter::format_source;
+///
+/// let source = "let  x:int=((1+2))*3 ;// nine\n\n\nprintln( x );";
+/// assert_eq!(
+///     format_source(source).unwrap(),
+///     "let x: int = (1 + 2) * 3; // nine\n\nprintln(x);\n"
+/// );
+/// ```
+pub fn format_source(source: &str) -> Result<String, Vec<ParseError>> {
+    let (tokens, comments) = tokenize(source)?;
+    let program = parse_tokens(source, &tokens)?;
+
+    let mut output = Output::default();
+    let mut comments = comments.into_iter().peekable();
+    format_block(&mut output, &mut comments, &program, &tokens, "");
+    for comment in comments {
+        output.comment(&comment, "");
+    }
+    Ok(output.text)
+}
+
+/// Formats `program`, whose tokens are `tokens`, with each line prefixed by `indent`,
+/// writing the comments that come before each statement's end along with it.
+fn format_block(
+    output: &mut Output,
+    comments: &mut Peekable<IntoIter<Comment>>,
+    program: &[Ast],
+    tokens: &[Token],
+    indent: &str,
+) {
+    let mut statement_tokens = statement_tokens(tokens);
+    for statement in program {
+        let tokens = statement_tokens
+            .next()
+            .expect("every statement ends with `;` or `}`");
+        let (first, last) = (&tokens[0], &tokens[tokens.len() - 1]);
+
+        if let Ast::Test { name, body } = statement {
+            while let Some(comment) = comments.next_if(|c| c.span.start < first.span.start) {
+                output.comment(&comment, indent);
+            }
+            let header = format!("{}test {} {{", indent, quote(name));
+            let open = &tokens[2];
+            if body.is_empty()
+                && comments
+                    .peek()
+                    .is_none_or(|c| c.span.start > last.span.start)
+            {
+                output.line(first.span.line, last.span.line, &format!("{}}}", header));
+            } else {
+                output.line(first.span.line, open.span.line, &header);
+                let body_tokens = test_body_tokens(tokens);
+                if body_tokens
+                    .first()
+                    .is_none_or(|t| t.span.line > open.span.line)
+                {
+                    while let Some(comment) = comments.next_if(|c| c.span.line == open.span.line) {
+                        output.trailing_comment(&comment);
+                    }
+                }
+                let inner = format!("{}{}", indent, INDENT);
+                format_block(output, comments, body, body_tokens, &inner);
+                while let Some(comment) = comments.next_if(|c| c.span.start < last.span.start) {
+                    output.comment(&comment, &inner);
+                }
+                output.line(last.span.line, last.span.line, &format!("{}}}", indent));
+            }
+        } else {
+            while let Some(comment) = comments.next_if(|c| c.span.start < last.span.start) {
+                output.comment(&comment, indent);
+            }
+            let text = format!("{}{}", indent, format_statement(statement));
+            output.line(first.span.line, last.span.line, &text);
+        }
+        while let Some(comment) = comments.next_if(|c| c.span.line == last.span.line) {
+            output.trailing_comment(&comment);
+        }
+    }
+}
+
+/// Prints a program without comments, one statement per line.
+pub fn format_program(program: &[Ast]) -> String {
+    program
+        .iter()
+        .map(|statement| format_statement(statement) + "\n")
+        .collect()
+}
+
+pub fn format_statement(statement: &Ast) -> String {
+    match statement {
+        Ast::VariableDeclaration {
+            name,
+            value_type,
+            expr,
+            public,
+        } => format!(
+            "{}let {}: {} = {};",
+            if *public { "pub " } else { "" },
+            name,
+            value_type.name(),
+            format_expression(expr)
+        ),
+        Ast::VariableAssignment {
+            name,
+            operator,
+            expr,
+        } => format!(
+            "{} {} {};",
+            name,
+            operator.symbol(),
+            format_expression(expr)
+        ),
+        Ast::Import { module, alias } => {
+            let module = match module {
+                ModulePath::File(path) => quote(path),
+                ModulePath::Name(name) => name.clone(),
+            };
+            match alias {
+                Some(alias) => format!("import {} as {};", module, alias),
+                None => format!("import {};", module),
+            }
+        }
+        Ast::Test { name, body } if body.is_empty() => format!("test {} {{}}", quote(name)),
+        Ast::Test { name, body } => {
+            let mut text = format!("test {} {{\n", quote(name));
+            for statement in body {
+                text.push_str(INDENT);
+                text.push_str(&format_statement(statement));
+                text.push('\n');
+            }
+            text.push('}');
+            text
+        }
+        expr => format!("{};", format_expression(expr)),
+    }
+}
+
+pub fn format_expression(expr: &Ast) -> String {
+    match expr {
+        Ast::Literal(value) => format_literal(value),
+        Ast::Variable(name) => name.clone(),
+        Ast::FunctionCall { name, arguments } => {
+            let arguments: Vec<String> = arguments.iter().map(format_expression).collect();
+            format!("{}({})", name, arguments.join(", "))
+        }
+        Ast::Expr {
+            left,
+            operator,
+            right,
+        } => {
+            let precedence = precedence(expr);
+            format!(
+                "{} {} {}",
+                operand(left, precedence, false),
+                operator.symbol(),
+                operand(right, precedence, true)
+            )
+        }
+        Ast::Monomial {
+            operator,
+            expr: inner,
+        } => {
+            let inner = operand(inner, precedence(expr), false);
+            // Keep `- -x` from reading like a decrement.
+            let space = if inner.starts_with(&['+', '-'][..]) {
+                " "
+            } else {
+                ""
+            };
+            format!("{}{}{}", operator.symbol(), space, inner)
+        }
+        // The grammar only allows comparisons of arithmetic and chains of comparisons
+        // joined by `&&`/`||`, none of which can be parenthesised, so none are needed.
+        Ast::ComparisonExpr {
+            left,
+            operator,
+            right,
+        } => format!(
+            "{} {} {}",
+            format_expression(left),
+            operator.symbol(),
+            format_expression(right)
+        ),
+        Ast::LogicalExpr {
+            left,
+            operator,
+            right,
+        } => format!(
+            "{} {} {}",
+            format_expression(left),
+            operator.symbol(),
+            format_expression(right)
+        ),
+        statement => format_statement(statement),
+    }
+}
+
+/// Formats `expr` as an operand of an operator binding with `parent` precedence.
+/// Operators are left-associative, so an equal-precedence right operand needs parentheses.
+fn operand(expr: &Ast, parent: u8, right: bool) -> String {
+    let own = precedence(expr);
+    if own < parent || (right && own == parent) {
+        format!("({})", format_expression(expr))
+    } else {
+        format_expression(expr)
+    }
+}
+
+fn precedence(expr: &Ast) -> u8 {
+    match expr {
+        Ast::LogicalExpr { .. } => 0,
+        Ast::ComparisonExpr { .. } => 1,
+        Ast::Expr {
+            operator: ExprOpKind::EAdd,
+            ..
+        }
+        | Ast::Expr {
+            operator: ExprOpKind::ESub,
+            ..
+        } => 2,
+        Ast::Expr { .. } => 3,
+        Ast::Monomial { .. } => 4,
+        _ => 5,
+    }
+}
+
+fn format_literal(value: &Value) -> String {
+    match value {
+        // `{:?}` always keeps a `.` or exponent, so the literal reads back as a float.
+        Value::Float(v) if v.is_finite() => format!("{:?}", v),
+        Value::Float(v) if v.is_nan() => "nan".to_string(),
+        Value::Float(v) if *v > 0.0 => "inf".to_string(),
+        Value::Float(_) => "-inf".to_string(),
+        Value::String(v) => quote(v),
+        value => value.to_string(),
+    }
+}
+
+/// Formatted text being built, and the source line its last piece came from, which
+/// decides where blank lines go.
+#[derive(Default)]
+struct Output {
+    text: String,
+    last_line: Option<usize>,
+}
+
+impl Output {
+    fn line(&mut self, first_line: usize, last_line: usize, text: &str) {
+        if let Some(previous) = self.last_line {
+            if first_line > previous + 1 {
+                self.text.push('\n');
+            }
+        }
+        self.text.push_str(text);
+        self.text.push('\n');
+        self.last_line = Some(last_line);
+    }
+
+    fn comment(&mut self, comment: &Comment, indent: &str) {
+        self.line(
+            comment.span.line,
+            comment_end_line(comment),
+            &format!("{}{}", indent, comment.text.trim_end()),
+        );
+    }
+
+    /// Appends `comment` to the line just written.
+    fn trailing_comment(&mut self, comment: &Comment) {
+        self.text.pop();
+        self.text.push(' ');
+        self.text.push_str(comment.text.trim_end());
+        self.text.push('\n');
+        self.last_line = Some(comment_end_line(comment));
+    }
+}
+
+fn comment_end_line(comment: &Comment) -> usize {
+    comment.span.line + comment.text.matches('\n').count()
+}
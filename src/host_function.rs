@@ -0,0 +1,108 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::machine::{Machine, MachineError};
+use crate::value::{FromValue, IntoValue, Value};
+
+/// Type-erased form in which `Machine` stores callable functions.
+pub type NativeFunction =
+    Rc<dyn Fn(&mut Machine, Vec<Value>) -> Result<Option<Value>, MachineError>>;
+
+/// Fails with `MachineError::ArgumentCount` unless exactly `expected` arguments were passed.
+pub fn check_arity(name: &str, arguments: &[Value], expected: usize) -> Result<(), MachineError> {
+    if arguments.len() != expected {
+        return Err(MachineError::ArgumentCount {
+            name: name.to_string(),
+            expected,
+            found: arguments.len(),
+        });
+    }
+    Ok(())
+}
+
+/// What a host function may return: a value, nothing, or a `Result` whose error
+/// is reported to the script as `MachineError::FunctionFailed`.
+pub trait IntoReturn {
+    fn into_return(self) -> Result<Option<Value>, String>;
+}
+
+impl IntoReturn for () {
+    fn into_return(self) -> Result<Option<Value>, String> {
+        Ok(None)
+    }
+}
+
+impl<T: IntoValue> IntoReturn for T {
+    fn into_return(self) -> Result<Option<Value>, String> {
+        Ok(Some(self.into_value()))
+    }
+}
+
+impl<T: IntoReturn, E: fmt::Display> IntoReturn for Result<T, E> {
+    fn into_return(self) -> Result<Option<Value>, String> {
+        match self {
+            Ok(v) => v.into_return(),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Rust closures that can be registered with `Machine::register_fn`.
+///
+/// `Args` is the tuple of argument types; it only exists to tell the arities apart.
+pub trait HostFunction<Args>: 'static {
+    fn into_native(self, name: &str) -> NativeFunction;
+}
+
+macro_rules! count {
+    () => { 0 };
+    ($head:ident $($tail:ident)*) => { 1 + count!($($tail)*) };
+}
+
+macro_rules! impl_host_function {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> HostFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoReturn,
+            $($arg: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_native(self, name: &str) -> NativeFunction {
+                let name = name.to_string();
+                Rc::new(move |_: &mut Machine, arguments: Vec<Value>| {
+                    check_arity(&name, &arguments, count!($($arg)*))?;
+                    let mut arguments = arguments.into_iter().enumerate();
+                    $(
+                        let $arg = {
+                            let (position, value) = arguments.next().unwrap();
+                            let found = value.type_name();
+                            <$arg as FromValue>::from_value(value).ok_or_else(|| {
+                                MachineError::ArgumentType {
+                                    name: name.clone(),
+                                    position: position + 1,
+                                    expected: <$arg as FromValue>::type_name(),
+                                    found: found.to_string(),
+                                }
+                            })?
+                        };
+                    )*
+                    (self)($($arg),*)
+                        .into_return()
+                        .map_err(|message| MachineError::FunctionFailed {
+                            name: name.clone(),
+                            message,
+                        })
+                })
+            }
+        }
+    };
+}
+
+impl_host_function!();
+impl_host_function!(A);
+impl_host_function!(A, B);
+impl_host_function!(A, B, C);
+impl_host_function!(A, B, C, D);
+impl_host_function!(A, B, C, D, E);
+impl_host_function!(A, B, C, D, E, G);
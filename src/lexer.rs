@@ -0,0 +1,400 @@
+mod comment;
+mod number;
+mod string;
+
+use std::fmt;
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while};
+use nom::character::complete::{multispace1, satisfy};
+use nom::combinator::{map, recognize, value};
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::sequence::pair;
+use nom::IResult;
+use unicode_normalization::UnicodeNormalization;
+use unicode_xid::UnicodeXID;
+
+use crate::parser::ParseError;
+use number::parse_number;
+use string::{parse_string, skip_string};
+
+pub use comment::{Comment, CommentKind};
+pub use string::quote;
+
+/// Location of a token in the source. `line` and `column` are 1-based and refer to `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// The source line this span starts on, with a caret under its first character.
+    pub fn excerpt(&self, source: &str) -> String {
+        let line_text = source.lines().nth(self.line - 1).unwrap_or("");
+        format!("{}\n{}^", line_text, " ".repeat(self.column - 1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    // Keywords
+    Let,
+    If,
+    Elif,
+    Else,
+    While,
+    For,
+    Break,
+    Continue,
+    Switch,
+    Case,
+    Default,
+    True,
+    False,
+
+    Identifier(String),
+    Integer(isize),
+    Float(f64),
+    Str(String),
+
+    // Operators
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Assign,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+    GreaterEqual,
+    LessEqual,
+    And,
+    Or,
+    Not,
+
+    // Punctuation
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Colon,
+    Semicolon,
+    Comma,
+    Dot,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl TokenKind {
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Let
+                | TokenKind::If
+                | TokenKind::Elif
+                | TokenKind::Else
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Switch
+                | TokenKind::Case
+                | TokenKind::Default
+                | TokenKind::True
+                | TokenKind::False
+        )
+    }
+
+    /// Source text of tokens without a payload.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TokenKind::Let => "let",
+            TokenKind::If => "if",
+            TokenKind::Elif => "elif",
+            TokenKind::Else => "else",
+            TokenKind::While => "while",
+            TokenKind::For => "for",
+            TokenKind::Break => "break",
+            TokenKind::Continue => "continue",
+            TokenKind::Switch => "switch",
+            TokenKind::Case => "case",
+            TokenKind::Default => "default",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Identifier(_) => "identifier",
+            TokenKind::Integer(_) => "integer",
+            TokenKind::Float(_) => "float",
+            TokenKind::Str(_) => "string",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Assign => "=",
+            TokenKind::PlusAssign => "+=",
+            TokenKind::MinusAssign => "-=",
+            TokenKind::StarAssign => "*=",
+            TokenKind::SlashAssign => "/=",
+            TokenKind::Equal => "==",
+            TokenKind::NotEqual => "!=",
+            TokenKind::Greater => ">",
+            TokenKind::Less => "<",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::LessEqual => "<=",
+            TokenKind::And => "&&",
+            TokenKind::Or => "||",
+            TokenKind::Not => "!",
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::LBrace => "{",
+            TokenKind::RBrace => "}",
+            TokenKind::Colon => ":",
+            TokenKind::Semicolon => ";",
+            TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Identifier(name) => write!(f, "identifier `{}`", name),
+            TokenKind::Integer(v) => write!(f, "integer `{}`", v),
+            TokenKind::Float(v) => write!(f, "float `{}`", v),
+            TokenKind::Str(v) => write!(f, "string {}", quote(v)),
+            kind if kind.is_keyword() => write!(f, "keyword `{}`", kind.symbol()),
+            kind => write!(f, "`{}`", kind.symbol()),
+        }
+    }
+}
+
+/// Splits `source` into tokens, collecting comments into a separate table.
+pub fn tokenize(source: &str) -> Result<(Vec<Token>, Vec<Comment>), Vec<ParseError>> {
+    let (tokens, comments, errors) = tokenize_recovering(source);
+    if errors.is_empty() {
+        Ok((tokens, comments))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Like `tokenize`, but skips past malformed input so that every error is reported.
+pub(crate) fn tokenize_recovering(source: &str) -> (Vec<Token>, Vec<Comment>, Vec<ParseError>) {
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut input = source;
+    let mut line = 1;
+    let mut column = 1;
+    while !input.is_empty() {
+        let start = source.len() - input.len();
+
+        let (remain, lexeme) = match parse_lexeme(input) {
+            Ok(v) => v,
+            Err(e) => {
+                let (message, placeholder) = match e {
+                    nom::Err::Failure(VerboseError { errors }) => match errors.first() {
+                        Some((_, VerboseErrorKind::Context(message))) => {
+                            // A malformed literal still stands for a value, so leave one
+                            // behind to keep the parser from reporting follow-up errors.
+                            let placeholder = if input.starts_with("/*") {
+                                Lexeme::Whitespace
+                            } else if input.starts_with('"') {
+                                Lexeme::Token(TokenKind::Str(String::new()))
+                            } else {
+                                Lexeme::Token(TokenKind::Integer(0))
+                            };
+                            (message.to_string(), placeholder)
+                        }
+                        _ => ("invalid token".to_string(), stray_placeholder(&tokens)),
+                    },
+                    _ => (
+                        format!("unexpected character `{}`", input.chars().next().unwrap()),
+                        stray_placeholder(&tokens),
+                    ),
+                };
+                let remain = skip_malformed(input);
+                let end = source.len() - remain.len();
+                errors.push(ParseError {
+                    span: Span {
+                        start,
+                        end,
+                        line,
+                        column,
+                    },
+                    expected: Vec::new(),
+                    found: format!("`{}`", &source[start..end]),
+                    message,
+                });
+                (remain, placeholder)
+            }
+        };
+        let end = source.len() - remain.len();
+        let span = Span {
+            start,
+            end,
+            line,
+            column,
+        };
+        match lexeme {
+            Lexeme::Whitespace => {}
+            Lexeme::Comment(kind, text) => comments.push(Comment {
+                kind,
+                text: text.to_string(),
+                span,
+            }),
+            Lexeme::Token(kind) => tokens.push(Token { kind, span }),
+        }
+
+        let consumed = &source[start..end];
+        match consumed.rfind('\n') {
+            Some(i) => {
+                line += consumed.matches('\n').count();
+                column = consumed[i + 1..].chars().count() + 1;
+            }
+            None => column += consumed.chars().count(),
+        }
+        input = remain;
+    }
+
+    (tokens, comments, errors)
+}
+
+/// What to leave in place of a character that starts no token, so that the parser
+/// does not report follow-up errors: after an operand it most likely stood for an
+/// operator, and anywhere else for a value.
+fn stray_placeholder(tokens: &[Token]) -> Lexeme<'static> {
+    match tokens.last().map(|t| &t.kind) {
+        Some(
+            TokenKind::Identifier(_)
+            | TokenKind::Integer(_)
+            | TokenKind::Float(_)
+            | TokenKind::Str(_)
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::RParen,
+        ) => Lexeme::Token(TokenKind::Plus),
+        _ => Lexeme::Token(TokenKind::Integer(0)),
+    }
+}
+
+// An unterminated block comment swallows the rest of the input and a malformed
+// string the rest of its line; anything else is skipped up to the next character
+// that could start a fresh token.
+fn skip_malformed(input: &str) -> &str {
+    if input.starts_with("/*") {
+        return "";
+    }
+    if input.starts_with('"') {
+        return skip_string(input);
+    }
+    let is_word = |c: char| UnicodeXID::is_xid_continue(c) || c == '.';
+    let mut chars = input.chars();
+    match chars.next() {
+        Some(c) if is_word(c) => input.trim_start_matches(is_word),
+        _ => chars.as_str(),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Lexeme<'a> {
+    Whitespace,
+    Comment(CommentKind, &'a str),
+    Token(TokenKind),
+}
+
+fn parse_lexeme(input: &str) -> IResult<&str, Lexeme<'_>, VerboseError<&str>> {
+    alt((
+        value(Lexeme::Whitespace, multispace1),
+        map(comment::parse_comment, |(kind, text)| {
+            Lexeme::Comment(kind, text)
+        }),
+        map(parse_number, Lexeme::Token),
+        map(parse_string, Lexeme::Token),
+        map(parse_word, Lexeme::Token),
+        map(parse_operator, Lexeme::Token),
+        map(parse_punctuation, Lexeme::Token),
+    ))(input)
+}
+
+fn parse_word(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    let (input, word) = recognize(pair(
+        satisfy(|c: char| UnicodeXID::is_xid_start(c) || c == '_'),
+        take_while(UnicodeXID::is_xid_continue),
+    ))(input)?;
+    // Visually identical names must refer to the same binding.
+    let word: String = word.nfc().collect();
+    let kind = match word.as_str() {
+        "let" => TokenKind::Let,
+        "if" => TokenKind::If,
+        "elif" => TokenKind::Elif,
+        "else" => TokenKind::Else,
+        "while" => TokenKind::While,
+        "for" => TokenKind::For,
+        "break" => TokenKind::Break,
+        "continue" => TokenKind::Continue,
+        "switch" => TokenKind::Switch,
+        "case" => TokenKind::Case,
+        "default" => TokenKind::Default,
+        "true" => TokenKind::True,
+        "false" => TokenKind::False,
+        "inf" => TokenKind::Float(f64::INFINITY),
+        "nan" => TokenKind::Float(f64::NAN),
+        _ => TokenKind::Identifier(word),
+    };
+    Ok((input, kind))
+}
+
+// Longer operators come first so that e.g. `>=` is never split into `>` and `=`.
+fn parse_operator(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    alt((
+        alt((
+            value(TokenKind::Equal, tag("==")),
+            value(TokenKind::NotEqual, tag("!=")),
+            value(TokenKind::GreaterEqual, tag(">=")),
+            value(TokenKind::LessEqual, tag("<=")),
+            value(TokenKind::And, tag("&&")),
+            value(TokenKind::Or, tag("||")),
+            value(TokenKind::PlusAssign, tag("+=")),
+            value(TokenKind::MinusAssign, tag("-=")),
+            value(TokenKind::StarAssign, tag("*=")),
+            value(TokenKind::SlashAssign, tag("/=")),
+        )),
+        alt((
+            value(TokenKind::Assign, tag("=")),
+            value(TokenKind::Greater, tag(">")),
+            value(TokenKind::Less, tag("<")),
+            value(TokenKind::Not, tag("!")),
+            value(TokenKind::Plus, tag("+")),
+            value(TokenKind::Minus, tag("-")),
+            value(TokenKind::Star, tag("*")),
+            value(TokenKind::Slash, tag("/")),
+        )),
+    ))(input)
+}
+
+fn parse_punctuation(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    alt((
+        value(TokenKind::LParen, tag("(")),
+        value(TokenKind::RParen, tag(")")),
+        value(TokenKind::LBrace, tag("{")),
+        value(TokenKind::RBrace, tag("}")),
+        value(TokenKind::Colon, tag(":")),
+        value(TokenKind::Semicolon, tag(";")),
+        value(TokenKind::Comma, tag(",")),
+        value(TokenKind::Dot, tag(".")),
+    ))(input)
+}
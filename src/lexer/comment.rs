@@ -0,0 +1,60 @@
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag};
+use nom::character::complete::anychar;
+use nom::combinator::{map, recognize};
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::multi::many0;
+use nom::sequence::pair;
+use nom::IResult;
+
+use super::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentKind {
+    Line,
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// Comment text including its `//` or `/* */` delimiters.
+    pub text: String,
+    pub span: Span,
+}
+
+pub fn parse_comment(input: &str) -> IResult<&str, (CommentKind, &str), VerboseError<&str>> {
+    alt((
+        map(parse_line_comment, |text| (CommentKind::Line, text)),
+        map(parse_block_comment, |text| (CommentKind::Block, text)),
+    ))(input)
+}
+
+fn parse_line_comment(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    recognize(pair(tag("//"), many0(is_not("\r\n"))))(input)
+}
+
+fn parse_block_comment(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    let (mut remain, _) = tag("/*")(input)?;
+    loop {
+        if let Ok((rest, _)) = tag::<&str, &str, VerboseError<&str>>("*/")(remain) {
+            let consumed = input.len() - rest.len();
+            return Ok((rest, &input[..consumed]));
+        }
+        if let Ok((rest, _)) = parse_block_comment(remain) {
+            remain = rest;
+            continue;
+        }
+        match anychar::<&str, VerboseError<&str>>(remain) {
+            Ok((rest, _)) => remain = rest,
+            Err(_) => {
+                return Err(nom::Err::Failure(VerboseError {
+                    errors: vec![(
+                        input,
+                        VerboseErrorKind::Context("unterminated block comment"),
+                    )],
+                }))
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while};
+use nom::character::complete::{char, digit1, one_of};
+use nom::combinator::{opt, recognize, value};
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::sequence::{pair, tuple};
+use nom::IResult;
+
+use super::TokenKind;
+
+pub fn parse_number(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    alt((parse_radix_integer, parse_float, parse_integer))(input)
+}
+
+fn parse_radix_integer(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    let (remain, radix) = alt((
+        value(16, alt((tag("0x"), tag("0X")))),
+        value(8, alt((tag("0o"), tag("0O")))),
+        value(2, alt((tag("0b"), tag("0B")))),
+    ))(input)?;
+    let (remain, digits_str) = take_while(|c: char| c.is_digit(radix) || c == '_')(remain)?;
+    let digits = digits_str.replace('_', "");
+    if digits.is_empty() {
+        return Err(literal_error(input, "expected digits after radix prefix"));
+    }
+    match isize::from_str_radix(&digits, radix) {
+        Ok(v) => Ok((remain, TokenKind::Integer(v))),
+        Err(_) => Err(literal_error(input, "integer literal out of range")),
+    }
+}
+
+fn parse_integer(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    let (remain, value_str) = parse_decimal_digits(input)?;
+    match value_str.replace('_', "").parse::<isize>() {
+        Ok(v) => Ok((remain, TokenKind::Integer(v))),
+        Err(_) => Err(literal_error(input, "integer literal out of range")),
+    }
+}
+
+fn parse_float(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    let (remain, value_str) = alt((
+        recognize(tuple((
+            parse_decimal_digits,
+            char('.'),
+            parse_decimal_digits,
+            opt(parse_exponent),
+        ))),
+        recognize(pair(parse_decimal_digits, parse_exponent)),
+        recognize(tuple((
+            char('.'),
+            parse_decimal_digits,
+            opt(parse_exponent),
+        ))),
+    ))(input)?;
+    match value_str.replace('_', "").parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok((remain, TokenKind::Float(v))),
+        _ => Err(literal_error(input, "float literal out of range")),
+    }
+}
+
+fn parse_decimal_digits(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    recognize(pair(
+        digit1,
+        take_while(|c: char| c.is_ascii_digit() || c == '_'),
+    ))(input)
+}
+
+fn parse_exponent(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    recognize(tuple((
+        one_of("eE"),
+        opt(one_of("+-")),
+        parse_decimal_digits,
+    )))(input)
+}
+
+fn literal_error<'a>(input: &'a str, message: &'static str) -> nom::Err<VerboseError<&'a str>> {
+    nom::Err::Failure(VerboseError {
+        errors: vec![(input, VerboseErrorKind::Context(message))],
+    })
+}
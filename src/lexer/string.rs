@@ -0,0 +1,74 @@
+use nom::character::complete::char;
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::IResult;
+
+use super::TokenKind;
+
+/// Parses a `"`-quoted string on a single line. `\"`, `\\`, `\n`, `\r` and `\t` are
+/// the only escapes.
+pub fn parse_string(input: &str) -> IResult<&str, TokenKind, VerboseError<&str>> {
+    let (mut remain, _) = char('"')(input)?;
+    let mut value = String::new();
+    loop {
+        let mut chars = remain.chars();
+        match chars.next() {
+            Some('"') => return Ok((chars.as_str(), TokenKind::Str(value))),
+            Some('\\') => {
+                let escaped = match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('\n') | None => return Err(string_error(input, "unterminated string")),
+                    Some(_) => return Err(string_error(input, "unknown escape sequence")),
+                };
+                value.push(escaped);
+            }
+            Some('\n') | None => return Err(string_error(input, "unterminated string")),
+            Some(c) => value.push(c),
+        }
+        remain = chars.as_str();
+    }
+}
+
+/// Writes `value` as a string literal that `parse_string` reads back.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The rest of a malformed string: through its closing quote, or to the end of the line.
+pub(super) fn skip_string(input: &str) -> &str {
+    let mut chars = input[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return &input[i + 2..],
+            '\n' => return &input[i + 1..],
+            '\\' => {
+                if let Some((i, '\n')) = chars.next() {
+                    return &input[i + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
+
+fn string_error<'a>(input: &'a str, message: &'static str) -> nom::Err<VerboseError<&'a str>> {
+    nom::Err::Failure(VerboseError {
+        errors: vec![(input, VerboseErrorKind::Context(message))],
+    })
+}
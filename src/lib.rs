@@ -1,5 +1,37 @@
+//! Whiteye language interpreter.
+//!
+//! The quickest way to run Whiteye code from Rust is through [`Engine`]:
+//!
+//! ```
+//! use whiteye::{Engine, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.register_fn("double", |x: isize| x * 2);
+//! let value = engine.eval("let x: int = 20; double(x) + 2").unwrap();
+//! assert_eq!(value, Value::Integer(42));
+//! ```
+//!
+//! Parse and runtime failures are both reported as [`Error`].
+
 pub mod ast;
 pub mod builtin_functions;
+pub mod debugger;
+pub mod engine;
+pub mod error;
+pub mod formatter;
+pub mod host_function;
+pub mod lexer;
+pub mod lint;
+pub mod lsp;
 pub mod machine;
+pub mod module;
 pub mod parser;
+pub mod profile;
+pub mod sandbox;
+pub mod snapshot;
+pub mod testing;
 pub mod value;
+
+pub use engine::Engine;
+pub use error::Error;
+pub use value::Value;
@@ -0,0 +1,575 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+
+use crate::ast::{AssignmentOpKind, Ast, ComparisonOpKind, ValueType};
+use crate::builtin_functions::constant;
+use crate::formatter::format_expression;
+use crate::lexer::{tokenize, Comment, CommentKind, Span, Token, TokenKind};
+use crate::parser::{parse_tokens, statement_tokens, test_body_tokens, ParseError};
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// A variable is declared but never read.
+    UnusedVariable,
+    /// A variable is assigned to without having been declared first.
+    UndeclaredAssignment,
+    /// A variable is read before its declaration, is never declared, or belongs to a
+    /// module that is not imported.
+    UseBeforeDeclare,
+    /// A comparison involves no variables or calls, so its result never changes.
+    ConstantCondition,
+    /// `x = x;`
+    SelfAssignment,
+    /// Floats compared with `==` or `!=`.
+    FloatEquality,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedVariable,
+        Rule::UndeclaredAssignment,
+        Rule::UseBeforeDeclare,
+        Rule::ConstantCondition,
+        Rule::SelfAssignment,
+        Rule::FloatEquality,
+    ];
+
+    /// The name used in config files and `// lint: allow(...)` comments.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UndeclaredAssignment => "undeclared-assignment",
+            Rule::UseBeforeDeclare => "use-before-declare",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::SelfAssignment => "self-assignment",
+            Rule::FloatEquality => "float-equality",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .iter()
+            .copied()
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| format!("unknown lint rule `{}`", s))
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Linter settings, usually read from `whiteye-lint.toml`:
+///
+/// ```toml
+/// globals = ["base"]
+///
+/// [rules]
+/// float-equality = false
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Variables the host defines before the script runs, e.g. with `Engine::set_var`.
+    pub globals: Vec<String>,
+    /// Rules switched on or off. Rules not listed are on.
+    pub rules: HashMap<Rule, bool>,
+}
+
+impl LintConfig {
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        self.rules.get(&rule).copied().unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub rule: Rule,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Warning {
+    /// Formats the warning together with the offending source line.
+    pub fn render(&self, source: &str) -> String {
+        format!("{}\n{}", self, self.span.excerpt(source))
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: warning: {} [{}]",
+            self.span.line, self.span.column, self.message, self.rule
+        )
+    }
+}
+
+/// Checks `source` against every enabled rule and returns the warnings in source order.
+///
+/// A `// lint: allow(rule, ...)` comment silences the listed rules for the statement
+/// it trails, or otherwise for the statement that follows it.
+///
+/// ```
+/// use whiteye::lint::{lint_source, LintConfig, Rule};
+///
+/// let source = "let x: int = 1;\nlet y: int = 2; // lint: allow(unused-variable)\nx = x;";
+/// let warnings = lint_source(source, &LintConfig::default()).unwrap();
+/// let rules: Vec<Rule> = warnings.iter().map(|w| w.rule).collect();
+/// assert_eq!(rules, vec![Rule::SelfAssignment]);
+/// ```
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Warning>, Vec<ParseError>> {
+    let (tokens, comments) = tokenize(source)?;
+    let program = parse_tokens(source, &tokens)?;
+    let statements: Vec<&[Token]> = statement_tokens(&tokens).collect();
+    let allowed = inline_allows(&comments, &statements);
+
+    let mut linter = Linter::new(config, &program);
+    for (index, (statement, tokens)) in program.iter().zip(&statements).enumerate() {
+        if !matches!(statement, Ast::Test { .. }) {
+            linter.statement(index, statement, tokens);
+        }
+    }
+    // Tests run after all of the top-level statements, so they are checked last.
+    for (index, (statement, tokens)) in program.iter().zip(&statements).enumerate() {
+        if let Ast::Test { body, .. } = statement {
+            linter.test(index, body, test_body_tokens(tokens));
+        }
+    }
+    linter.finish();
+
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|(index, w)| config.is_enabled(w.rule) && !allowed[*index].contains(&w.rule))
+        .map(|(_, w)| w)
+        .collect();
+    warnings.sort_by_key(|w| w.span.start);
+    Ok(warnings)
+}
+
+/// Rules allowed by comments, per statement. A comment inside a test block applies
+/// to the whole block.
+fn inline_allows(comments: &[Comment], statements: &[&[Token]]) -> Vec<HashSet<Rule>> {
+    let mut allowed = vec![HashSet::new(); statements.len()];
+    for comment in comments {
+        let rules = match allow_directive(comment) {
+            Some(rules) => rules,
+            None => continue,
+        };
+        let trailed = statements.iter().position(|tokens| {
+            let last = &tokens[tokens.len() - 1];
+            last.span.line == comment.span.line && last.span.start < comment.span.start
+        });
+        let within = statements.iter().position(|tokens| {
+            tokens[0].span.start < comment.span.start
+                && comment.span.start < tokens[tokens.len() - 1].span.start
+        });
+        let target = trailed.or(within).or_else(|| {
+            statements
+                .iter()
+                .position(|t| t[0].span.start > comment.span.start)
+        });
+        if let Some(index) = target {
+            allowed[index].extend(rules);
+        }
+    }
+    allowed
+}
+
+/// The rules named by a `lint: allow(...)` comment. Unknown names are ignored.
+fn allow_directive(comment: &Comment) -> Option<Vec<Rule>> {
+    let text = match comment.kind {
+        CommentKind::Line => &comment.text[2..],
+        CommentKind::Block => &comment.text[2..comment.text.len() - 2],
+    };
+    let list = text
+        .trim()
+        .strip_prefix("lint:")?
+        .trim()
+        .strip_prefix("allow(")?
+        .strip_suffix(')')?;
+    Some(
+        list.split(',')
+            .filter_map(|r| r.trim().parse().ok())
+            .collect(),
+    )
+}
+
+#[derive(Clone)]
+struct Declaration<'a> {
+    value_type: &'a ValueType,
+    span: Span,
+    statement: usize,
+    used: bool,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    /// Every name declared somewhere in the program, to tell "declared later" from
+    /// "never declared".
+    declared_anywhere: HashSet<&'a str>,
+    variables: HashMap<&'a str, Declaration<'a>>,
+    /// Aliases of the modules imported so far.
+    modules: HashSet<String>,
+    warnings: Vec<(usize, Warning)>,
+}
+
+/// Walks the tokens of one statement's expression alongside its AST, to find where
+/// each variable and comparison is in the source.
+struct Cursor<'t> {
+    statement: usize,
+    tokens: &'t [Token],
+    variables: usize,
+    comparisons: usize,
+}
+
+impl<'t> Cursor<'t> {
+    fn new(statement: usize, tokens: &'t [Token]) -> Self {
+        Cursor {
+            statement,
+            tokens,
+            variables: 0,
+            comparisons: 0,
+        }
+    }
+
+    /// The next identifier that is not a function name. For `module.name` it is the
+    /// module's.
+    fn next_variable(&mut self) -> Span {
+        while let Some(token) = self.tokens.get(self.variables) {
+            self.variables += 1;
+            if matches!(
+                self.tokens.get(self.variables),
+                Some(Token {
+                    kind: TokenKind::Dot,
+                    ..
+                })
+            ) {
+                self.variables += 2;
+            }
+            let is_call = matches!(
+                self.tokens.get(self.variables),
+                Some(Token {
+                    kind: TokenKind::LParen,
+                    ..
+                })
+            );
+            if matches!(token.kind, TokenKind::Identifier(_)) && !is_call {
+                return token.span;
+            }
+        }
+        self.fallback()
+    }
+
+    fn peek_comparison(&self) -> Span {
+        self.tokens[self.comparisons..]
+            .iter()
+            .find(|t| is_comparison(&t.kind))
+            .map_or_else(|| self.fallback(), |t| t.span)
+    }
+
+    fn next_comparison(&mut self) -> Span {
+        while let Some(token) = self.tokens.get(self.comparisons) {
+            self.comparisons += 1;
+            if is_comparison(&token.kind) {
+                return token.span;
+            }
+        }
+        self.fallback()
+    }
+
+    fn fallback(&self) -> Span {
+        self.tokens.first().map_or_else(Span::default, |t| t.span)
+    }
+}
+
+fn is_comparison(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Equal
+            | TokenKind::NotEqual
+            | TokenKind::Greater
+            | TokenKind::Less
+            | TokenKind::GreaterEqual
+            | TokenKind::LessEqual
+    )
+}
+
+impl<'a> Linter<'a> {
+    fn new(config: &'a LintConfig, program: &'a [Ast]) -> Self {
+        let declared_anywhere = program
+            .iter()
+            .filter_map(|statement| match statement {
+                Ast::VariableDeclaration { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        Linter {
+            config,
+            declared_anywhere,
+            variables: HashMap::new(),
+            modules: HashSet::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, statement: usize, rule: Rule, span: Span, message: String) {
+        self.warnings.push((
+            statement,
+            Warning {
+                rule,
+                span,
+                message,
+            },
+        ));
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.variables.contains_key(name) || self.config.globals.iter().any(|g| g == name)
+    }
+
+    fn statement(&mut self, index: usize, statement: &'a Ast, tokens: &[Token]) {
+        match statement {
+            Ast::VariableDeclaration {
+                name,
+                value_type,
+                expr,
+                public,
+            } => {
+                let value_start = tokens
+                    .iter()
+                    .position(|t| t.kind == TokenKind::Assign)
+                    .map_or(tokens.len(), |i| i + 1);
+                self.expression(expr, &mut Cursor::new(index, &tokens[value_start..]), false);
+                let name_token = if *public { 2 } else { 1 };
+                let declaration = Declaration {
+                    value_type,
+                    span: tokens[name_token].span,
+                    statement: index,
+                    // Importing modules may read it.
+                    used: *public,
+                };
+                if let Some(previous) = self.variables.insert(name, declaration) {
+                    self.check_used(name, &previous);
+                }
+            }
+
+            Ast::VariableAssignment {
+                name,
+                operator,
+                expr,
+            } => {
+                let span = tokens[0].span;
+                if !self.is_declared(name) {
+                    let message = if self.declared_anywhere.contains(name.as_str()) {
+                        format!("assignment to `{}` before its declaration", name)
+                    } else {
+                        format!("assignment to undeclared variable `{}`", name)
+                    };
+                    self.warn(index, Rule::UndeclaredAssignment, span, message);
+                }
+                if *operator == AssignmentOpKind::AEqual {
+                    if let Ast::Variable(value) = &**expr {
+                        if value == name {
+                            let message = format!("`{}` is assigned to itself", name);
+                            self.warn(index, Rule::SelfAssignment, span, message);
+                        }
+                    }
+                }
+                self.expression(expr, &mut Cursor::new(index, &tokens[2..]), false);
+            }
+
+            Ast::Import { module, alias } => {
+                let alias = alias.clone().unwrap_or_else(|| module.default_alias());
+                self.modules.insert(alias);
+            }
+
+            expr => self.expression(expr, &mut Cursor::new(index, tokens), false),
+        }
+    }
+
+    /// Checks a test body, whose declarations go out of scope at its end.
+    fn test(&mut self, index: usize, body: &'a [Ast], tokens: &[Token]) {
+        let outer = self.variables.clone();
+        for (statement, tokens) in body.iter().zip(statement_tokens(tokens)) {
+            self.statement(index, statement, tokens);
+        }
+        let inner = std::mem::replace(&mut self.variables, outer);
+        for (name, declaration) in inner {
+            match self.variables.get_mut(name) {
+                Some(outer) if outer.span == declaration.span => outer.used |= declaration.used,
+                _ => self.check_used(name, &declaration),
+            }
+        }
+    }
+
+    /// `reported` is set below a condition already reported as constant, so that its
+    /// parts are not reported again.
+    fn expression(&mut self, expr: &'a Ast, cursor: &mut Cursor, reported: bool) {
+        match expr {
+            Ast::Literal(_) => {}
+
+            Ast::Variable(name) => {
+                let span = cursor.next_variable();
+                self.read(name, span, cursor.statement);
+            }
+
+            Ast::FunctionCall { arguments, .. } => {
+                for argument in arguments {
+                    self.expression(argument, cursor, false);
+                }
+            }
+
+            Ast::Expr { left, right, .. } => {
+                self.expression(left, cursor, reported);
+                self.expression(right, cursor, reported);
+            }
+
+            Ast::Monomial { expr, .. } => self.expression(expr, cursor, reported),
+
+            Ast::ComparisonExpr {
+                left,
+                operator,
+                right,
+            } => {
+                let reported = self.check_constant(expr, cursor, reported);
+                self.expression(left, cursor, reported);
+                let span = cursor.next_comparison();
+                if matches!(operator, ComparisonOpKind::CEqual | ComparisonOpKind::CNot)
+                    && (self.is_float(left) || self.is_float(right))
+                {
+                    let message = "floats compared for exact equality; compare their \
+                                   difference with a tolerance instead"
+                        .to_string();
+                    self.warn(cursor.statement, Rule::FloatEquality, span, message);
+                }
+                self.expression(right, cursor, reported);
+            }
+
+            Ast::LogicalExpr { left, right, .. } => {
+                let reported = self.check_constant(expr, cursor, reported);
+                self.expression(left, cursor, reported);
+                self.expression(right, cursor, reported);
+            }
+
+            Ast::VariableDeclaration { .. }
+            | Ast::VariableAssignment { .. }
+            | Ast::Import { .. }
+            | Ast::Test { .. } => {}
+        }
+    }
+
+    /// Reports `condition` if it is constant, returning whether it or an enclosing
+    /// condition has been reported.
+    fn check_constant(&mut self, condition: &Ast, cursor: &Cursor, reported: bool) -> bool {
+        if reported {
+            return true;
+        }
+        if is_constant(condition) {
+            let message = format!("condition `{}` is constant", format_expression(condition));
+            let span = cursor.peek_comparison();
+            self.warn(cursor.statement, Rule::ConstantCondition, span, message);
+            return true;
+        }
+        false
+    }
+
+    fn read(&mut self, name: &str, span: Span, statement: usize) {
+        if let Some((module, _)) = name.split_once('.') {
+            if !self.modules.contains(module) {
+                let message = format!("module `{}` is not imported", module);
+                self.warn(statement, Rule::UseBeforeDeclare, span, message);
+            }
+            return;
+        }
+        if let Some(declaration) = self.variables.get_mut(name) {
+            declaration.used = true;
+            return;
+        }
+        if self.config.globals.iter().any(|g| g == name) || constant(name).is_some() {
+            return;
+        }
+        let message = if self.declared_anywhere.contains(name) {
+            format!("`{}` is used before its declaration", name)
+        } else {
+            format!("`{}` is not declared", name)
+        };
+        self.warn(statement, Rule::UseBeforeDeclare, span, message);
+    }
+
+    fn check_used(&mut self, name: &str, declaration: &Declaration) {
+        if !declaration.used && !name.starts_with('_') {
+            let message = format!("variable `{}` is never used", name);
+            self.warn(
+                declaration.statement,
+                Rule::UnusedVariable,
+                declaration.span,
+                message,
+            );
+        }
+    }
+
+    fn finish(&mut self) {
+        let variables: Vec<(&str, Declaration)> = self.variables.drain().collect();
+        for (name, declaration) in variables {
+            self.check_used(name, &declaration);
+        }
+    }
+
+    fn is_float(&self, expr: &Ast) -> bool {
+        match expr {
+            Ast::Literal(value) => matches!(value, Value::Float(_)),
+            Ast::Variable(name) => matches!(
+                self.variables.get(name.as_str()),
+                Some(Declaration {
+                    value_type: ValueType::Float,
+                    ..
+                })
+            ),
+            Ast::Expr { left, right, .. } => self.is_float(left) || self.is_float(right),
+            Ast::Monomial { expr, .. } => self.is_float(expr),
+            _ => false,
+        }
+    }
+}
+
+/// Whether `expr` involves neither variables nor function calls.
+fn is_constant(expr: &Ast) -> bool {
+    match expr {
+        Ast::Literal(_) => true,
+        Ast::Variable(_) | Ast::FunctionCall { .. } => false,
+        Ast::Expr { left, right, .. }
+        | Ast::ComparisonExpr { left, right, .. }
+        | Ast::LogicalExpr { left, right, .. } => is_constant(left) && is_constant(right),
+        Ast::Monomial { expr, .. } => is_constant(expr),
+        Ast::VariableDeclaration { .. }
+        | Ast::VariableAssignment { .. }
+        | Ast::Import { .. }
+        | Ast::Test { .. } => false,
+    }
+}
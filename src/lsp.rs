@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value as Json};
+
+use crate::builtin_functions::CONSTANTS;
+use crate::lexer::{tokenize_recovering, Span, TokenKind};
+use crate::lint::{lint_source, LintConfig};
+use crate::machine::Machine;
+use crate::parser::parse;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Values of the LSP enums used below.
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const COMPLETION_FUNCTION: u8 = 3;
+const COMPLETION_VARIABLE: u8 = 6;
+const COMPLETION_CONSTANT: u8 = 21;
+const SYMBOL_VARIABLE: u8 = 13;
+
+/// Serves the Language Server Protocol over `input` and `output` until the client
+/// sends `exit` or closes the stream.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match serde_json::from_str(&body) {
+            Ok(message) => server.handle(message),
+            Err(e) => vec![error_response(Json::Null, PARSE_ERROR, e.to_string())],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.has_exited() {
+            break;
+        }
+    }
+    output.flush()
+}
+
+/// Reads the body of one message framed with a `Content-Length` header, or `None`
+/// at end of input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// State of one editor session: the open documents and the built-in function names
+/// offered as completions.
+pub struct Server {
+    documents: HashMap<String, String>,
+    functions: Vec<String>,
+    shutting_down: bool,
+    exited: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            documents: HashMap::new(),
+            functions: Machine::new()
+                .function_names()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            shutting_down: false,
+            exited: false,
+        }
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handles one request or notification, returning the messages to send back.
+    pub fn handle(&mut self, message: Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let reply = if self.shutting_down {
+                    Err((INVALID_REQUEST, "server is shutting down".to_string()))
+                } else {
+                    self.request(method, params)
+                };
+                vec![match reply {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, message)) => error_response(id.clone(), code, message),
+                }]
+            }
+            None => self.notification(method, params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": {"name": "whiteye", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shutting_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => {
+                let (uri, source, offset) = self.position_params(params)?;
+                let symbols = index(source);
+                Ok(symbol_at(&symbols, offset)
+                    .and_then(|symbol| definition(&symbols, symbol))
+                    .map_or(Json::Null, |d| location(uri, source, d.span)))
+            }
+            "textDocument/references" => {
+                let (uri, source, offset) = self.position_params(params)?;
+                let include_declaration = params["context"]["includeDeclaration"]
+                    .as_bool()
+                    .unwrap_or(true);
+                let symbols = index(source);
+                let references: Vec<Json> = match symbol_at(&symbols, offset) {
+                    Some(symbol) if symbol.occurrence != Occurrence::Function => symbols
+                        .iter()
+                        .filter(|s| s.name == symbol.name)
+                        .filter(|s| match s.occurrence {
+                            Occurrence::Declaration(_) => include_declaration,
+                            Occurrence::Variable => true,
+                            Occurrence::Function => false,
+                        })
+                        .map(|s| location(uri, source, s.span))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                Ok(Json::from(references))
+            }
+            "textDocument/hover" => {
+                let (_, source, offset) = self.position_params(params)?;
+                let symbols = index(source);
+                let symbol = match symbol_at(&symbols, offset) {
+                    Some(symbol) => symbol,
+                    None => return Ok(Json::Null),
+                };
+                let text = match &symbol.occurrence {
+                    Occurrence::Function if self.functions.contains(&symbol.name) => {
+                        format!("built-in function `{}`", symbol.name)
+                    }
+                    Occurrence::Function => return Ok(Json::Null),
+                    _ => match definition(&symbols, symbol).map(|d| &d.occurrence) {
+                        Some(Occurrence::Declaration(value_type)) => {
+                            format!("```whiteye\nlet {}: {}\n```", symbol.name, value_type)
+                        }
+                        _ => return Ok(Json::Null),
+                    },
+                };
+                Ok(json!({
+                    "contents": {"kind": "markdown", "value": text},
+                    "range": range(source, symbol.span),
+                }))
+            }
+            "textDocument/completion" => {
+                let (_, source, offset) = self.position_params(params)?;
+                let symbols = index(source);
+                let mut variables: Vec<(&str, &str)> = Vec::new();
+                for symbol in &symbols {
+                    if let Occurrence::Declaration(value_type) = &symbol.occurrence {
+                        if symbol.span.end < offset {
+                            variables.retain(|(name, _)| *name != symbol.name);
+                            variables.push((&symbol.name, value_type));
+                        }
+                    }
+                }
+                let mut items: Vec<Json> = variables
+                    .into_iter()
+                    .map(|(name, value_type)| {
+                        json!({"label": name, "kind": COMPLETION_VARIABLE, "detail": value_type})
+                    })
+                    .collect();
+                items.extend(
+                    self.functions
+                        .iter()
+                        .map(|name| json!({"label": name, "kind": COMPLETION_FUNCTION})),
+                );
+                items.extend(CONSTANTS.iter().map(|(name, _)| {
+                    json!({"label": name, "kind": COMPLETION_CONSTANT, "detail": "float"})
+                }));
+                Ok(Json::from(items))
+            }
+            "textDocument/documentSymbol" => {
+                let source = self.document(params)?.1;
+                let symbols: Vec<Json> = index(source)
+                    .iter()
+                    .filter_map(|symbol| match &symbol.occurrence {
+                        Occurrence::Declaration(value_type) => Some(json!({
+                            "name": symbol.name,
+                            "detail": value_type,
+                            "kind": SYMBOL_VARIABLE,
+                            "range": range(source, symbol.span),
+                            "selectionRange": range(source, symbol.span),
+                        })),
+                        _ => None,
+                    })
+                    .collect();
+                Ok(Json::from(symbols))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                // Only full-document sync is advertised, so the last change is the whole text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            "exit" => {
+                self.exited = true;
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Syntax errors, or lint warnings for documents that parse.
+    fn diagnostics(&self, uri: &str) -> Json {
+        let source = self.documents.get(uri).map_or("", String::as_str);
+        let diagnostics = match parse(source) {
+            Err(errors) => errors
+                .iter()
+                .map(|e| diagnostic(source, e.span, SEVERITY_ERROR, None, &e.message))
+                .collect(),
+            Ok(_) => lint_source(source, &LintConfig::default())
+                .unwrap_or_default()
+                .iter()
+                .map(|w| {
+                    diagnostic(
+                        source,
+                        w.span,
+                        SEVERITY_WARNING,
+                        Some(w.rule.name()),
+                        &w.message,
+                    )
+                })
+                .collect(),
+        };
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    fn document<'p>(&self, params: &'p Json) -> Result<(&'p str, &str), (i64, String)> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let source = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document {} is not open", uri)))?;
+        Ok((uri, source))
+    }
+
+    fn position_params<'p>(
+        &self,
+        params: &'p Json,
+    ) -> Result<(&'p str, &str, usize), (i64, String)> {
+        let (uri, source) = self.document(params)?;
+        let offset = offset(source, &params["position"])
+            .ok_or((INVALID_PARAMS, "invalid position".to_string()))?;
+        Ok((uri, source, offset))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Occurrence {
+    /// `let name: type`, with the type as written.
+    Declaration(String),
+    Variable,
+    Function,
+}
+
+#[derive(Debug)]
+struct Symbol {
+    name: String,
+    span: Span,
+    occurrence: Occurrence,
+}
+
+/// Every identifier in `source` that names a variable or function. Built from tokens
+/// alone so that it keeps working while the document has syntax errors.
+fn index(source: &str) -> Vec<Symbol> {
+    let (tokens, _, _) = tokenize_recovering(source);
+    let kind = |i: usize| tokens.get(i).map(|t| &t.kind);
+    let mut symbols = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let name = match &token.kind {
+            TokenKind::Identifier(name) => name,
+            _ => continue,
+        };
+        let previous = i.checked_sub(1).and_then(kind);
+        let occurrence = if previous == Some(&TokenKind::Let) {
+            let value_type = match (kind(i + 1), kind(i + 2)) {
+                (Some(TokenKind::Colon), Some(TokenKind::Identifier(t))) => t.clone(),
+                _ => "?".to_string(),
+            };
+            Occurrence::Declaration(value_type)
+        } else if previous == Some(&TokenKind::Colon)
+            && i.checked_sub(3).and_then(kind) == Some(&TokenKind::Let)
+        {
+            // The type in `let name: type`.
+            continue;
+        } else if kind(i + 1) == Some(&TokenKind::LParen) {
+            Occurrence::Function
+        } else if kind(i + 1) == Some(&TokenKind::Dot) || previous == Some(&TokenKind::Dot) {
+            // The module and the name in `module.name` are not local variables.
+            continue;
+        } else {
+            Occurrence::Variable
+        };
+        symbols.push(Symbol {
+            name: name.clone(),
+            span: token.span,
+            occurrence,
+        });
+    }
+    symbols
+}
+
+fn symbol_at(symbols: &[Symbol], offset: usize) -> Option<&Symbol> {
+    symbols
+        .iter()
+        .find(|s| s.span.start <= offset && offset <= s.span.end)
+}
+
+/// The declaration in force at `symbol`: the last one before it, or failing that the
+/// first one after it.
+fn definition<'s>(symbols: &'s [Symbol], symbol: &Symbol) -> Option<&'s Symbol> {
+    if symbol.occurrence == Occurrence::Function {
+        return None;
+    }
+    let mut declarations = symbols
+        .iter()
+        .filter(|s| s.name == symbol.name && matches!(s.occurrence, Occurrence::Declaration(_)));
+    let first = declarations.next()?;
+    Some(
+        std::iter::once(first)
+            .chain(declarations)
+            .take_while(|d| d.span.start <= symbol.span.start)
+            .last()
+            .unwrap_or(first),
+    )
+}
+
+fn error_response(id: Json, code: i64, message: String) -> Json {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn diagnostic(source: &str, span: Span, severity: u8, code: Option<&str>, message: &str) -> Json {
+    let mut diagnostic = json!({
+        "range": range(source, span),
+        "severity": severity,
+        "source": "whiteye",
+        "message": message,
+    });
+    if let Some(code) = code {
+        diagnostic["code"] = Json::from(code);
+    }
+    diagnostic
+}
+
+fn location(uri: &str, source: &str, span: Span) -> Json {
+    json!({"uri": uri, "range": range(source, span)})
+}
+
+fn range(source: &str, span: Span) -> Json {
+    json!({"start": position(source, span.start), "end": position(source, span.end)})
+}
+
+/// Converts a byte offset to an LSP position, whose character counts UTF-16 units.
+fn position(source: &str, offset: usize) -> Json {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+/// Converts an LSP position to a byte offset, or `None` if it is past the end of the text.
+fn offset(source: &str, position: &Json) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    let line_start = if line == 0 {
+        0
+    } else {
+        source.match_indices('\n').nth(line - 1)?.0 + 1
+    };
+    let mut units = 0;
+    for (i, c) in source[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(source.len())
+}
@@ -1,13 +1,26 @@
-use core::panic;
-use std::collections::HashMap;
-use std::convert::TryInto;
-use std::error::Error;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use crate::ast::{
-    AssignmentOpKind, Ast, ComparisonOpKind, ExprOpKind, LogicalOpKind, UnaryOpKind, ValueType,
+    AssignmentOpKind, Ast, ComparisonOpKind, ExprOpKind, LogicalOpKind, ModulePath, UnaryOpKind,
+    ValueType,
 };
 
-use crate::{builtin_functions, value::Value};
+use crate::builtin_functions;
+use crate::host_function::{HostFunction, NativeFunction};
+use crate::lexer::Span;
+use crate::module::{Module, Modules};
+use crate::parser::parse_with_spans;
+use crate::sandbox::{Capabilities, Capability, InterruptHandle, Limits, Progress};
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::value::Value;
 
 #[derive(Debug, thiserror::Error)]
 pub enum MachineError {
@@ -15,11 +28,234 @@ pub enum MachineError {
     VariableUndefined(String),
     #[error("Invalid Function Name: {0}")]
     InvalidFunctionName(String),
+    #[error("Function {name} expects {expected} argument(s), found {found}")]
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Function {name} argument {position}: expected {expected}, found {found}")]
+    ArgumentType {
+        name: String,
+        position: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("Function {name} failed: {message}")]
+    FunctionFailed { name: String, message: String },
+    #[error("Function {0} does not return a value")]
+    NoReturnValue(String),
+    #[error("I/O Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Step limit of {0} exceeded")]
+    StepLimitExceeded(u64),
+    #[error("Call depth limit of {0} exceeded")]
+    CallDepthExceeded(usize),
+    #[error("Expression nesting limit of {0} exceeded")]
+    ExpressionDepthExceeded(usize),
+    #[error("Function {name} returned a value of size {size}, over the limit of {limit}")]
+    SizeLimitExceeded {
+        name: String,
+        size: usize,
+        limit: usize,
+    },
+    #[error("Joining strings into a value of size {size} is over the limit of {limit}")]
+    ConcatenationTooLong { size: usize, limit: usize },
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("Function {name} needs {capability} access, which is disabled")]
+    CapabilityDisabled {
+        name: String,
+        capability: Capability,
+    },
+    #[error("Interrupted")]
+    Interrupted,
+    #[error("Assertion failed: {0}")]
+    AssertionFailed(String),
+    #[error(
+        "Assertion failed: left != right\n  left:  {left} ({})\n  right: {right} ({})",
+        .left.type_name(),
+        .right.type_name()
+    )]
+    AssertEqFailed { left: Value, right: Value },
+    #[error("Cannot apply `{operator}` to {left} and {right}")]
+    TypeMismatch {
+        operator: &'static str,
+        left: &'static str,
+        right: &'static str,
+    },
+    #[error("Cannot apply unary `{operator}` to {operand}")]
+    UnaryTypeMismatch {
+        operator: &'static str,
+        operand: &'static str,
+    },
+    #[error("Variable {name} is declared {declared}, but its value is {found}")]
+    DeclarationTypeMismatch {
+        name: String,
+        declared: &'static str,
+        found: &'static str,
+    },
+    #[error("Expected a statement, found {0}")]
+    NotAStatement(&'static str),
+    #[error("Expected an expression, found {0}")]
+    NotAnExpression(&'static str),
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Integer overflow in {0}")]
+    IntegerOverflow(String),
+    #[error("Aborted by the host")]
+    Aborted,
+    #[error("Module {module} not found; looked for {}", display_paths(.searched, ", "))]
+    ModuleNotFound {
+        module: String,
+        searched: Vec<PathBuf>,
+    },
+    #[error("Import cycle: {}", display_paths(.0, " -> "))]
+    ImportCycle(Vec<PathBuf>),
+    #[error("Syntax error in module {}:{error}", .path.display())]
+    ModuleSyntax { path: PathBuf, error: String },
+    #[error("In module {}:{line}:{column}: {error}", .path.display())]
+    InModule {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        error: Box<MachineError>,
+    },
+    #[error("Module {0} is not imported")]
+    ModuleUndefined(String),
+    #[error("Module {module} does not export {name}")]
+    NotExported { module: String, name: String },
+    #[error("Imports need file system access, which is disabled")]
+    ImportDisabled,
+}
+
+fn display_paths(paths: &[PathBuf], separator: &str) -> String {
+    let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    paths.join(separator)
+}
+
+struct Function {
+    native: NativeFunction,
+    capability: Option<Capability>,
+}
+
+/// Receives control around each statement and function call; implemented by
+/// debuggers, tracers and profilers.
+pub trait DebugHook {
+    /// Called with the statement about to run. `Machine::location` tells where it is
+    /// in the source, if the driver supplied that. Returning `Progress::Abort` stops
+    /// the machine with `MachineError::Aborted`.
+    fn before_statement(&mut self, machine: &mut Machine, statement: &Ast) -> Progress;
+
+    /// Called once the statement has run, whether or not it succeeded.
+    fn after_statement(&mut self, _machine: &Machine, _statement: &Ast) {}
+
+    /// Called with a function's name just before it runs, after its arguments.
+    fn enter_function(&mut self, _machine: &Machine, _name: &str) {}
+
+    /// Called when the function entered last returns, whether or not it succeeded.
+    fn exit_function(&mut self, _machine: &Machine, _name: &str) {}
+}
+
+/// Lets the host keep a handle on a hook, for example to read a profile after the run.
+impl<H: DebugHook> DebugHook for Rc<RefCell<H>> {
+    fn before_statement(&mut self, machine: &mut Machine, statement: &Ast) -> Progress {
+        self.borrow_mut().before_statement(machine, statement)
+    }
+
+    fn after_statement(&mut self, machine: &Machine, statement: &Ast) {
+        self.borrow_mut().after_statement(machine, statement)
+    }
+
+    fn enter_function(&mut self, machine: &Machine, name: &str) {
+        self.borrow_mut().enter_function(machine, name)
+    }
+
+    fn exit_function(&mut self, machine: &Machine, name: &str) {
+        self.borrow_mut().exit_function(machine, name)
+    }
+}
+
+struct ProgressCallback {
+    every: u64,
+    callback: Box<dyn FnMut(&Machine) -> Progress>,
 }
 
-#[derive(Debug, Default)]
 pub struct Machine {
     pub variables: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+    output: Box<dyn Write>,
+    error_output: Box<dyn Write>,
+    input: Box<dyn BufRead>,
+    args: Vec<String>,
+    limits: Limits,
+    capabilities: Capabilities,
+    steps: u64,
+    started: Option<Instant>,
+    call_depth: usize,
+    expression_depth: usize,
+    interrupt: InterruptHandle,
+    progress: Option<ProgressCallback>,
+    debug_hook: Option<Box<dyn DebugHook>>,
+    location: Option<Span>,
+    modules: Modules,
+    /// Modules imported by the file being run, by alias.
+    imports: HashMap<String, Rc<Module>>,
+    /// Names the file being run declared `pub`.
+    exports: Vec<String>,
+}
+
+/// Adapts a closure receiving text to `io::Write`, for `Machine::set_output_fn`.
+struct OutputFn<F>(F);
+
+impl<F: FnMut(&str)> Write for OutputFn<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.0)(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        let mut machine = Machine {
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            output: Box::new(io::stdout()),
+            error_output: Box::new(io::stderr()),
+            input: Box::new(BufReader::new(io::stdin())),
+            args: Vec::new(),
+            limits: Limits::default(),
+            capabilities: Capabilities::default(),
+            steps: 0,
+            started: None,
+            call_depth: 0,
+            expression_depth: 0,
+            interrupt: InterruptHandle::default(),
+            progress: None,
+            debug_hook: None,
+            location: None,
+            modules: Modules::default(),
+            imports: HashMap::new(),
+            exports: Vec::new(),
+        };
+        builtin_functions::register(&mut machine);
+        machine
+    }
+}
+
+impl fmt::Debug for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Machine")
+            .field("variables", &self.variables)
+            .field("functions", &self.function_names())
+            .field("limits", &self.limits)
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
 }
 
 impl Machine {
@@ -27,19 +263,405 @@ impl Machine {
         Self::default()
     }
 
-    pub fn run(&mut self, expr: Ast) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    /// Makes a Rust closure callable from scripts as `name(...)`.
+    ///
+    /// Arguments are converted with `FromValue` and the result with `IntoValue`; a
+    /// wrong argument count or type is reported as a `MachineError` when called.
+    ///
+    /// ```
+    /// use whiteye::machine::Machine;
+    ///
+    /// let mut machine = Machine::new();
+    /// machine.register_fn("add", |a: isize, b: isize| a + b);
+    /// ```
+    pub fn register_fn<Args, F: HostFunction<Args>>(&mut self, name: &str, function: F) {
+        self.insert_function(name, function.into_native(name), None);
+    }
+
+    /// Like `register_fn`, but calls fail with `MachineError::CapabilityDisabled`
+    /// while `capability` is not granted.
+    pub fn register_fn_with_capability<Args, F: HostFunction<Args>>(
+        &mut self,
+        name: &str,
+        capability: Capability,
+        function: F,
+    ) {
+        self.insert_function(name, function.into_native(name), Some(capability));
+    }
+
+    /// Registers a function that needs access to the machine itself, such as the
+    /// printing built-ins.
+    pub(crate) fn register_native(&mut self, name: &str, function: NativeFunction) {
+        self.insert_function(name, function, None);
+    }
+
+    pub(crate) fn register_native_with_capability(
+        &mut self,
+        name: &str,
+        capability: Capability,
+        function: NativeFunction,
+    ) {
+        self.insert_function(name, function, Some(capability));
+    }
+
+    fn insert_function(
+        &mut self,
+        name: &str,
+        native: NativeFunction,
+        capability: Option<Capability>,
+    ) {
+        self.functions
+            .insert(name.to_string(), Function { native, capability });
+    }
+
+    /// Names of all registered functions, sorted.
+    pub fn function_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Replaces the limits checked while running, keeping the usage counted so far.
+    ///
+    /// ```
+    /// use whiteye::machine::{Machine, MachineError};
+    /// use whiteye::parser::parse;
+    /// use whiteye::sandbox::Limits;
+    ///
+    /// let mut machine = Machine::new();
+    /// machine.set_limits(Limits {
+    ///     max_steps: Some(10),
+    ///     ..Limits::default()
+    /// });
+    /// let ast = parse("let x: int = 1 + 2 + 3 + 4 + 5 + 6;").unwrap().remove(0);
+    /// assert!(matches!(machine.run(ast), Err(MachineError::StepLimitExceeded(10))));
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Starts a fresh step count and timeout window.
+    pub fn reset_usage(&mut self) {
+        self.steps = 0;
+        self.started = None;
+    }
+
+    /// Steps counted since the last `reset_usage`.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns a handle through which another thread can stop this machine.
+    ///
+    /// ```
+    /// use std::thread;
+    /// use whiteye::machine::{Machine, MachineError};
+    /// use whiteye::parser::parse;
+    ///
+    /// let mut machine = Machine::new();
+    /// let handle = machine.interrupt_handle();
+    /// thread::spawn(move || handle.interrupt()).join().unwrap();
+    ///
+    /// let ast = parse("let x: int = 1;").unwrap().remove(0);
+    /// assert!(matches!(machine.run(ast), Err(MachineError::Interrupted)));
+    /// ```
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Calls `callback` once every `every` steps. Returning `Progress::Abort` stops
+    /// the machine with `MachineError::Aborted`.
+    ///
+    /// ```
+    /// use whiteye::machine::{Machine, MachineError};
+    /// use whiteye::parser::parse;
+    /// use whiteye::sandbox::Progress;
+    ///
+    /// let mut machine = Machine::new();
+    /// machine.set_progress_callback(2, |machine| {
+    ///     if machine.steps() < 4 {
+    ///         Progress::Continue
+    ///     } else {
+    ///         Progress::Abort
+    ///     }
+    /// });
+    /// let ast = parse("let x: int = 1 + 2 + 3;").unwrap().remove(0);
+    /// assert!(matches!(machine.run(ast), Err(MachineError::Aborted)));
+    /// ```
+    pub fn set_progress_callback(
+        &mut self,
+        every: u64,
+        callback: impl FnMut(&Machine) -> Progress + 'static,
+    ) {
+        assert!(every > 0, "progress callback interval must be positive");
+        self.progress = Some(ProgressCallback {
+            every,
+            callback: Box::new(callback),
+        });
+    }
+
+    pub fn clear_progress_callback(&mut self) {
+        self.progress = None;
+    }
+
+    /// Captures the machine's variables and imports so that they can be saved and later
+    /// restored.
+    ///
+    /// ```
+    /// use whiteye::machine::Machine;
+    /// use whiteye::parser::parse;
+    /// use whiteye::snapshot::Snapshot;
+    /// use whiteye::Value;
+    ///
+    /// let mut machine = Machine::new();
+    /// machine.run(parse("let x: int = 42;").unwrap().remove(0)).unwrap();
+    /// let saved = machine.snapshot().to_json();
+    ///
+    /// let mut restored = Machine::new();
+    /// restored.restore(Snapshot::from_json(&saved).unwrap()).unwrap();
+    /// assert_eq!(restored.variables.get("x"), Some(&Value::Integer(42)));
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        let variables: BTreeMap<String, Value> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let imports = self
+            .imports
+            .iter()
+            .map(|(alias, module)| (alias.clone(), Module::clone(module)))
+            .collect();
+        let mut modules: Vec<Module> = self
+            .modules
+            .loaded
+            .values()
+            .map(|module| Module::clone(module))
+            .collect();
+        modules.sort_by(|a, b| a.path.cmp(&b.path));
+        let functions = self
+            .function_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        Snapshot::new(variables, imports, modules, functions, self.steps)
+    }
+
+    /// Replaces the variables, imports and step count with those of `snapshot`. Fails,
+    /// leaving the machine untouched, if a function the snapshot's machine had is not
+    /// registered.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), SnapshotError> {
+        if let Some(missing) = snapshot
+            .functions
+            .iter()
+            .find(|name| !self.functions.contains_key(*name))
+        {
+            return Err(SnapshotError::MissingFunction(missing.clone()));
+        }
+        self.variables = snapshot.variables.into_iter().collect();
+        self.imports = snapshot
+            .imports
+            .into_iter()
+            .map(|(alias, module)| (alias, Rc::new(module)))
+            .collect();
+        self.modules.loaded = snapshot
+            .modules
+            .into_iter()
+            .map(|module| (module.path.clone(), Rc::new(module)))
+            .collect();
+        self.steps = snapshot.steps;
+        self.started = None;
+        Ok(())
+    }
+
+    pub fn set_debug_hook(&mut self, hook: impl DebugHook + 'static) {
+        self.debug_hook = Some(Box::new(hook));
+    }
+
+    pub fn clear_debug_hook(&mut self) {
+        self.debug_hook = None;
+    }
+
+    /// Where the statement being run starts in the source, when run with `run_at`.
+    pub fn location(&self) -> Option<Span> {
+        self.location
+    }
+
+    /// Sets the file being run, which `import`s are resolved relative to. Without it
+    /// they are resolved relative to the current directory.
+    pub fn set_script_path(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.modules.script = Some(path);
+    }
+
+    /// Sets the directories `import name;` looks for `name.wy` in, after the
+    /// importing file's own directory; see `module::module_path_from_env`.
+    pub fn set_module_path(&mut self, directories: Vec<PathBuf>) {
+        self.modules.search_path = directories;
+    }
+
+    pub fn module_path(&self) -> &[PathBuf] {
+        &self.modules.search_path
+    }
+
+    /// Whether the statement being run belongs to an imported module rather than the
+    /// file being run, in which case `location` is in the module's source.
+    pub fn in_module(&self) -> bool {
+        !self.modules.loading.is_empty()
+    }
+
+    /// How many function calls are in progress.
+    pub fn call_depth(&self) -> usize {
+        self.call_depth
+    }
+
+    /// Sends everything scripts print to `output` instead of stdout.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Sends everything scripts print to `output`. Each printing call is passed as one piece.
+    ///
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use whiteye::machine::Machine;
+    /// use whiteye::parser::parse;
+    ///
+    /// let printed = Rc::new(RefCell::new(String::new()));
+    /// let sink = printed.clone();
+    ///
+    /// let mut machine = Machine::new();
+    /// machine.set_output_fn(move |text| sink.borrow_mut().push_str(text));
+    /// for ast in parse("println(1 + 2);").unwrap() {
+    ///     machine.run(ast).unwrap();
+    /// }
+    /// assert_eq!(*printed.borrow(), "3\n");
+    /// ```
+    pub fn set_output_fn(&mut self, output: impl FnMut(&str) + 'static) {
+        self.output = Box::new(OutputFn(output));
+    }
+
+    /// Sends what scripts write with `eprint` to `output` instead of stderr.
+    pub fn set_error_output(&mut self, output: impl Write + 'static) {
+        self.error_output = Box::new(output);
+    }
+
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        &mut self.error_output
+    }
+
+    /// Sets the command-line arguments scripts get from `args()`.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Makes scripts read their input from `input` instead of stdin.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.input = Box::new(input);
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    /// Reads one line of input without its line ending, or `None` at end of input.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Runs `statement`, recording `span` as its location for hooks.
+    pub fn run_at(&mut self, statement: Ast, span: Span) -> Result<(), MachineError> {
+        self.location = Some(span);
+        let result = self.run_statement(statement);
+        self.location = None;
+        result
+    }
+
+    pub fn run(&mut self, expr: Ast) -> Result<(), MachineError> {
+        self.location = None;
+        self.run_statement(expr)
+    }
+
+    fn run_statement(&mut self, expr: Ast) -> Result<(), MachineError> {
+        self.step()?;
+        let hooked = match self.debug_hook.take() {
+            Some(mut hook) => {
+                let decision = hook.before_statement(self, &expr);
+                self.debug_hook = Some(hook);
+                if decision == Progress::Abort {
+                    return Err(MachineError::Aborted);
+                }
+                Some(expr.clone())
+            }
+            None => None,
+        };
+        let result = self.execute(expr);
+        if let Some(statement) = hooked {
+            self.with_debug_hook(|hook, machine| hook.after_statement(machine, &statement));
+        }
+        result
+    }
+
+    /// Lends the debug hook, if any, to `f` along with the machine.
+    fn with_debug_hook(&mut self, f: impl FnOnce(&mut dyn DebugHook, &Machine)) {
+        if let Some(mut hook) = self.debug_hook.take() {
+            f(hook.as_mut(), self);
+            self.debug_hook = Some(hook);
+        }
+    }
+
+    fn execute(&mut self, expr: Ast) -> Result<(), MachineError> {
         match expr {
             Ast::VariableDeclaration {
                 name,
                 value_type,
                 expr,
+                public,
             } => {
-                let variable_value = match value_type {
-                    ValueType::Integer => self.eval_expression(*expr),
-                    ValueType::Float => self.eval_expression(*expr),
-                    ValueType::Bool => self.eval_expression(*expr),
+                // An int is widened into a float variable, as for float arguments.
+                let variable_value = match (&value_type, self.eval_expression(*expr)?) {
+                    (ValueType::Float, Value::Integer(v)) => Value::Float(v as f64),
+                    (_, value) if value.type_name() == value_type.name() => value,
+                    (_, value) => {
+                        return Err(MachineError::DeclarationTypeMismatch {
+                            name,
+                            declared: value_type.name(),
+                            found: value.type_name(),
+                        })
+                    }
                 };
 
+                if public && !self.exports.contains(&name) {
+                    self.exports.push(name.clone());
+                }
                 self.variables.insert(name, variable_value);
 
                 Ok(())
@@ -50,160 +672,412 @@ impl Machine {
                 operator,
                 expr,
             } => {
-                let variable_expr = self.eval_expression(*expr);
+                let variable_expr = self.eval_expression(*expr)?;
                 let variable_value = match self.variables.get(&name) {
-                    Some(v) => match v {
-                        Value::Integer(value) => Value::Integer(*value),
-                        Value::Float(value) => Value::Float(*value),
-                        Value::Bool(value) => Value::Bool(*value),
-                    },
-                    None => return Err(MachineError::VariableUndefined(name).into()),
+                    Some(v) => v.clone(),
+                    None => return Err(MachineError::VariableUndefined(name)),
                 };
                 let new_variable_value = match operator {
                     AssignmentOpKind::AEqual => variable_expr,
-                    AssignmentOpKind::AAdd => variable_expr + variable_value,
-                    AssignmentOpKind::ASub => variable_expr - variable_value,
-                    AssignmentOpKind::AMul => variable_expr * variable_value,
-                    AssignmentOpKind::ADiv => variable_expr / variable_value,
+                    AssignmentOpKind::AAdd => {
+                        self.binary(variable_value, ExprOpKind::EAdd, variable_expr)?
+                    }
+                    AssignmentOpKind::ASub => {
+                        self.binary(variable_value, ExprOpKind::ESub, variable_expr)?
+                    }
+                    AssignmentOpKind::AMul => {
+                        self.binary(variable_value, ExprOpKind::EMul, variable_expr)?
+                    }
+                    AssignmentOpKind::ADiv => {
+                        self.binary(variable_value, ExprOpKind::EDiv, variable_expr)?
+                    }
                 };
                 match self.variables.get_mut(&name) {
                     Some(v) => *v = new_variable_value,
-                    None => return Err(MachineError::VariableUndefined(name).into()),
+                    None => return Err(MachineError::VariableUndefined(name)),
                 };
 
                 Ok(())
             }
 
-            Ast::FunctionCall { name, argument } => {
-                match name.as_ref() {
-                    "print" => builtin_functions::print(self.eval_expression(*argument)),
-                    _ => return Err(MachineError::InvalidFunctionName(name).into()),
-                };
+            Ast::FunctionCall { name, arguments } => {
+                self.call_function(&name, arguments)?;
 
                 Ok(())
             }
 
-            _ => panic!(),
+            Ast::Import { module, alias } => {
+                let alias = alias.unwrap_or_else(|| module.default_alias());
+                let module = self.import(&module)?;
+                self.imports.insert(alias, module);
+
+                Ok(())
+            }
+
+            // Tests only run under `whiteye test`.
+            Ast::Test { .. } => Ok(()),
+
+            // Only reachable from ASTs built by hand, as the parser never puts
+            // expressions in statement position.
+            expr => Err(MachineError::NotAStatement(expr.kind())),
         }
     }
 
-    pub fn eval_expression(&mut self, expr: Ast) -> Value {
+    /// Evaluates `expr`, counting it against the step and expression depth limits.
+    pub fn eval_expression(&mut self, expr: Ast) -> Result<Value, MachineError> {
+        self.step()?;
+        if let Some(max) = self.limits.max_expression_depth {
+            if self.expression_depth >= max {
+                return Err(MachineError::ExpressionDepthExceeded(max));
+            }
+        }
+        self.expression_depth += 1;
+        let value = self.evaluate(expr);
+        self.expression_depth -= 1;
+        value
+    }
+
+    // Each kind of node is handled in its own function to keep this frame small:
+    // it is on the stack once per nesting level.
+    fn evaluate(&mut self, expr: Ast) -> Result<Value, MachineError> {
         match expr {
-            Ast::Literal(v) => match v {
-                Value::Integer(value) => Value::Integer(value),
-                Value::Float(value) => Value::Float(value),
-                Value::Bool(value) => Value::Bool(value),
-            },
+            Ast::Literal(v) => Ok(v),
 
-            Ast::Variable(name) => match self.variables.get(&name).unwrap() {
-                Value::Integer(value) => Value::Integer(*value),
-                Value::Float(value) => Value::Float(*value),
-                Value::Bool(value) => Value::Bool(*value),
+            Ast::Variable(name) => self.variable(name),
+
+            Ast::FunctionCall { name, arguments } => match self.call_function(&name, arguments)? {
+                Some(v) => Ok(v),
+                None => Err(MachineError::NoReturnValue(name)),
             },
 
             Ast::Expr {
                 left,
-                operator: ExprOpKind::EAdd,
+                operator,
                 right,
-            } => self.eval_expression(*left) + self.eval_expression(*right),
+            } => self.eval_arithmetic(*left, operator, *right),
 
-            Ast::Expr {
-                left,
-                operator: ExprOpKind::ESub,
-                right,
-            } => self.eval_expression(*left) - self.eval_expression(*right),
+            Ast::Monomial { operator, expr } => self.eval_unary(operator, *expr),
 
-            Ast::Expr {
+            Ast::ComparisonExpr {
                 left,
-                operator: ExprOpKind::EMul,
+                operator,
                 right,
-            } => self.eval_expression(*left) * self.eval_expression(*right),
+            } => self.eval_comparison(*left, operator, *right),
 
-            Ast::Expr {
+            Ast::LogicalExpr {
                 left,
-                operator: ExprOpKind::EDiv,
+                operator,
                 right,
-            } => self.eval_expression(*left) / self.eval_expression(*right),
+            } => self.eval_logical(*left, operator, *right),
 
-            Ast::Monomial {
-                operator: UnaryOpKind::UPlus,
-                expr,
-            } => self.eval_expression(*expr),
+            statement => Err(MachineError::NotAnExpression(statement.kind())),
+        }
+    }
 
-            Ast::Monomial {
-                operator: UnaryOpKind::UMinus,
-                expr,
-            } => -self.eval_expression(*expr),
+    /// Reads a variable, a built-in constant, or `module.name` from an imported module.
+    pub(crate) fn variable(&self, name: String) -> Result<Value, MachineError> {
+        if let Some(v) = self.variables.get(&name) {
+            return Ok(v.clone());
+        }
+        if let Some(v) = builtin_functions::constant(&name) {
+            return Ok(v);
+        }
+        match name.split_once('.') {
+            Some((module, member)) => self
+                .imported(module)?
+                .exports
+                .get(member)
+                .cloned()
+                .ok_or_else(|| MachineError::NotExported {
+                    module: module.to_string(),
+                    name: member.to_string(),
+                }),
+            None => Err(MachineError::VariableUndefined(name)),
+        }
+    }
 
-            Ast::ComparisonExpr {
-                left,
-                operator: ComparisonOpKind::CEqual,
-                right,
-            } => Value::from(self.eval_expression(*left) == self.eval_expression(*right)),
+    fn imported(&self, alias: &str) -> Result<&Module, MachineError> {
+        match self.imports.get(alias) {
+            Some(module) => Ok(module),
+            None => Err(MachineError::ModuleUndefined(alias.to_string())),
+        }
+    }
 
-            Ast::ComparisonExpr {
-                left,
-                operator: ComparisonOpKind::CNot,
-                right,
-            } => Value::from(self.eval_expression(*left) != self.eval_expression(*right)),
+    /// Runs the module `module` names, unless it has already been run, and returns
+    /// what it exports.
+    /// A module file found for `import name;` takes the place of the built-in module
+    /// of that name. Without file system access only built-in modules can be imported.
+    fn import(&mut self, module: &ModulePath) -> Result<Rc<Module>, MachineError> {
+        let builtin = match module {
+            ModulePath::Name(name) => builtin_functions::module(name),
+            ModulePath::File(_) => None,
+        };
+        if !self.capabilities.allows(Capability::FileSystem) {
+            return builtin.map(Rc::new).ok_or(MachineError::ImportDisabled);
+        }
+        let path = match (self.modules.resolve(module), builtin) {
+            (Ok(path), _) => path,
+            (Err(_), Some(builtin)) => return Ok(Rc::new(builtin)),
+            (Err(e), None) => return Err(e),
+        };
+        if let Some(module) = self.modules.loaded.get(&path) {
+            return Ok(module.clone());
+        }
+        if let Some(chain) = self.modules.cycle(&path) {
+            return Err(MachineError::ImportCycle(chain));
+        }
 
-            Ast::ComparisonExpr {
-                left,
-                operator: ComparisonOpKind::CGreater,
-                right,
-            } => Value::from(self.eval_expression(*left) > self.eval_expression(*right)),
+        let source = fs::read_to_string(&path)?;
+        let program = parse_with_spans(&source).map_err(|errors| MachineError::ModuleSyntax {
+            path: path.clone(),
+            error: errors[0].to_string(),
+        })?;
 
-            Ast::ComparisonExpr {
-                left,
-                operator: ComparisonOpKind::CLess,
-                right,
-            } => Value::from(self.eval_expression(*left) < self.eval_expression(*right)),
+        // The module runs in a scope of its own, as if it were the file being run.
+        let variables = mem::take(&mut self.variables);
+        let imports = mem::take(&mut self.imports);
+        let exports = mem::take(&mut self.exports);
+        let location = self.location;
+        self.modules.loading.push(path.clone());
+        let result = program.into_iter().try_for_each(|(statement, span)| {
+            self.run_at(statement, span).map_err(|error| (error, span))
+        });
+        self.modules.loading.pop();
+        self.location = location;
+        let module_variables = mem::replace(&mut self.variables, variables);
+        self.imports = imports;
+        let module_exports = mem::replace(&mut self.exports, exports);
 
-            Ast::ComparisonExpr {
-                left,
-                operator: ComparisonOpKind::CGreaterEqual,
-                right,
-            } => Value::from(self.eval_expression(*left) >= self.eval_expression(*right)),
+        if let Err((error, span)) = result {
+            return Err(match error {
+                MachineError::Aborted | MachineError::Interrupted => error,
+                error => MachineError::InModule {
+                    path,
+                    line: span.line,
+                    column: span.column,
+                    error: Box::new(error),
+                },
+            });
+        }
+        let module = Rc::new(Module {
+            exports: module_exports
+                .into_iter()
+                .filter_map(|name| {
+                    let value = module_variables.get(&name)?.clone();
+                    Some((name, value))
+                })
+                .collect(),
+            path: path.clone(),
+            functions: BTreeSet::new(),
+        });
+        self.modules.loaded.insert(path, module.clone());
+        Ok(module)
+    }
 
-            Ast::ComparisonExpr {
-                left,
-                operator: ComparisonOpKind::CLessEqual,
-                right,
-            } => Value::from(self.eval_expression(*left) <= self.eval_expression(*right)),
+    fn eval_arithmetic(
+        &mut self,
+        left: Ast,
+        operator: ExprOpKind,
+        right: Ast,
+    ) -> Result<Value, MachineError> {
+        let left = self.eval_expression(left)?;
+        let right = self.eval_expression(right)?;
+        self.binary(left, operator, right)
+    }
 
-            Ast::LogicalExpr {
-                left,
-                operator: LogicalOpKind::LAnd,
-                right,
-            } => {
-                let left_value = match self.eval_expression(*left).try_into() {
-                    Ok(v) => v,
-                    Err(_) => panic!(),
-                };
-                let right_value = match self.eval_expression(*right).try_into() {
-                    Ok(v) => v,
-                    Err(_) => panic!(),
-                };
-                Value::from(left_value && right_value)
+    fn eval_unary(&mut self, operator: UnaryOpKind, expr: Ast) -> Result<Value, MachineError> {
+        let value = self.eval_expression(expr)?;
+        match (operator, value) {
+            (UnaryOpKind::UPlus, value @ (Value::Integer(_) | Value::Float(_))) => Ok(value),
+            (UnaryOpKind::UMinus, value) => -value,
+            (operator, value) => Err(MachineError::UnaryTypeMismatch {
+                operator: operator.symbol(),
+                operand: value.type_name(),
+            }),
+        }
+    }
+
+    fn eval_comparison(
+        &mut self,
+        left: Ast,
+        operator: ComparisonOpKind,
+        right: Ast,
+    ) -> Result<Value, MachineError> {
+        let left = self.eval_expression(left)?;
+        let right = self.eval_expression(right)?;
+        let ordering = !matches!(operator, ComparisonOpKind::CEqual | ComparisonOpKind::CNot);
+        if ordering && !left.is_comparable_with(&right) {
+            return Err(MachineError::TypeMismatch {
+                operator: operator.symbol(),
+                left: left.type_name(),
+                right: right.type_name(),
+            });
+        }
+        Ok(Value::from(match operator {
+            ComparisonOpKind::CEqual => left == right,
+            ComparisonOpKind::CNot => left != right,
+            ComparisonOpKind::CGreater => left > right,
+            ComparisonOpKind::CLess => left < right,
+            ComparisonOpKind::CGreaterEqual => left >= right,
+            ComparisonOpKind::CLessEqual => left <= right,
+        }))
+    }
+
+    fn eval_logical(
+        &mut self,
+        left: Ast,
+        operator: LogicalOpKind,
+        right: Ast,
+    ) -> Result<Value, MachineError> {
+        let (left_value, right_value) =
+            match (self.eval_expression(left)?, self.eval_expression(right)?) {
+                (Value::Bool(l), Value::Bool(r)) => (l, r),
+                (l, r) => {
+                    return Err(MachineError::TypeMismatch {
+                        operator: operator.symbol(),
+                        left: l.type_name(),
+                        right: r.type_name(),
+                    })
+                }
+            };
+        Ok(Value::from(match operator {
+            LogicalOpKind::LAnd => left_value && right_value,
+            LogicalOpKind::LOr => left_value || right_value,
+        }))
+    }
+
+    fn call_function(
+        &mut self,
+        name: &str,
+        arguments: Vec<Ast>,
+    ) -> Result<Option<Value>, MachineError> {
+        let arguments = arguments
+            .into_iter()
+            .map(|argument| self.eval_expression(argument))
+            .collect::<Result<Vec<Value>, MachineError>>()?;
+        let function = match self.functions.get(name) {
+            Some(f) => f,
+            // Only built-in modules export functions, which are the unqualified ones.
+            None => match name.split_once('.') {
+                Some((module, member)) => match self.functions.get(member) {
+                    Some(f) if self.imported(module)?.functions.contains(member) => f,
+                    _ => {
+                        self.imported(module)?;
+                        return Err(MachineError::NotExported {
+                            module: module.to_string(),
+                            name: member.to_string(),
+                        });
+                    }
+                },
+                None => return Err(MachineError::InvalidFunctionName(name.to_string())),
+            },
+        };
+        if let Some(capability) = function.capability {
+            if !self.capabilities.allows(capability) {
+                return Err(MachineError::CapabilityDisabled {
+                    name: name.to_string(),
+                    capability,
+                });
+            }
+        }
+        if self.interrupt.is_interrupted() {
+            return Err(MachineError::Interrupted);
+        }
+        if let Some(max) = self.limits.max_call_depth {
+            if self.call_depth >= max {
+                return Err(MachineError::CallDepthExceeded(max));
             }
+        }
 
-            Ast::LogicalExpr {
-                left,
-                operator: LogicalOpKind::LOr,
-                right,
-            } => {
-                let left_value = match self.eval_expression(*left).try_into() {
-                    Ok(v) => v,
-                    Err(_) => panic!(),
-                };
-                let right_value = match self.eval_expression(*right).try_into() {
-                    Ok(v) => v,
-                    Err(_) => panic!(),
-                };
-                Value::from(left_value || right_value)
+        let native = function.native.clone();
+        self.with_debug_hook(|hook, machine| hook.enter_function(machine, name));
+        self.call_depth += 1;
+        let result = native(self, arguments);
+        self.call_depth -= 1;
+        self.with_debug_hook(|hook, machine| hook.exit_function(machine, name));
+
+        let result = result?;
+        if let Some(value) = &result {
+            self.check_size(name, value_size(value))?;
+        }
+        Ok(result)
+    }
+
+    /// Fails if a value of `size`, as counted for `Limits::max_value_size`, is over
+    /// the limit. Built-ins that build large values call this before allocating them.
+    pub(crate) fn check_size(&self, name: &str, size: usize) -> Result<(), MachineError> {
+        match self.limits.max_value_size {
+            Some(limit) if size > limit => Err(MachineError::SizeLimitExceeded {
+                name: name.to_string(),
+                size,
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// `left operator right`, checking that joined strings stay within the size limit.
+    fn binary(
+        &self,
+        left: Value,
+        operator: ExprOpKind,
+        right: Value,
+    ) -> Result<Value, MachineError> {
+        if let (Value::String(l), Value::String(r), Some(limit)) =
+            (&left, &right, self.limits.max_value_size)
+        {
+            let size = l.len().saturating_add(r.len());
+            if operator == ExprOpKind::EAdd && size > limit {
+                return Err(MachineError::ConcatenationTooLong { size, limit });
             }
+        }
+        match operator {
+            ExprOpKind::EAdd => left + right,
+            ExprOpKind::ESub => left - right,
+            ExprOpKind::EMul => left * right,
+            ExprOpKind::EDiv => left / right,
+        }
+    }
 
-            _ => unreachable!(),
+    /// Counts one step, checks the step and time limits and the interrupt flag, and
+    /// runs the progress callback when it is due.
+    fn step(&mut self) -> Result<(), MachineError> {
+        if self.interrupt.is_interrupted() {
+            return Err(MachineError::Interrupted);
         }
+        self.steps += 1;
+        if let Some(max) = self.limits.max_steps {
+            if self.steps > max {
+                return Err(MachineError::StepLimitExceeded(max));
+            }
+        }
+        if let Some(timeout) = self.limits.timeout {
+            let started = *self.started.get_or_insert_with(Instant::now);
+            if started.elapsed() > timeout {
+                return Err(MachineError::Timeout(timeout));
+            }
+        }
+        if let Some(mut progress) = self.progress.take() {
+            let due = self.steps.is_multiple_of(progress.every);
+            let decision = if due {
+                (progress.callback)(self)
+            } else {
+                Progress::Continue
+            };
+            self.progress = Some(progress);
+            if decision == Progress::Abort {
+                return Err(MachineError::Aborted);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes of a string, or elements of a list including those of nested lists and strings.
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::List(items) => items.len() + items.iter().map(value_size).sum::<usize>(),
+        _ => 0,
     }
 }
@@ -1,17 +1,114 @@
 use anyhow::Result;
-use clap::{crate_description, crate_name, crate_version, App, Arg};
+use clap::{
+    crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand,
+};
 use log::{debug, LevelFilter};
+use std::cell::RefCell;
 use std::fs;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
 
-use whiteye::machine::Machine;
-use whiteye::parser::parse;
+use whiteye::ast;
+use whiteye::debugger::{dap, Debugger};
+use whiteye::formatter;
+use whiteye::lint::{lint_source, LintConfig};
+use whiteye::lsp;
+use whiteye::machine::{Machine, MachineError};
+use whiteye::module::module_path_from_env;
+use whiteye::parser::parse_with_spans;
+use whiteye::profile::{Profiler, Tracer};
+use whiteye::testing;
 
 fn main() -> Result<()> {
     let app = App::new(crate_name!())
         .about(crate_description!())
         .version(crate_version!())
+        .setting(AppSettings::TrailingVarArg)
         .arg(Arg::with_name("FILE"))
-        .arg(Arg::with_name("dbg").short("d").long("debug"));
+        .arg(
+            Arg::with_name("ARGS")
+                .multiple(true)
+                .allow_hyphen_values(true)
+                .help("Passed to the script, which reads them with args(); put -- before them if the first starts with -"),
+        )
+        .arg(Arg::with_name("dbg").short("d").long("debug"))
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .conflicts_with("trace")
+                .help("Prints per-statement and per-function hotspots to stderr"),
+        )
+        .arg(
+            Arg::with_name("folded")
+                .long("folded")
+                .takes_value(true)
+                .value_name("FILE")
+                .requires("profile")
+                .help("Also writes the profile as folded stacks for flamegraph tools"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .help("Prints each statement and the variables it changes to stderr"),
+        )
+        .arg(
+            Arg::with_name("emit-ast")
+                .long("emit-ast")
+                .takes_value(true)
+                .possible_values(&["json"])
+                .help("Prints the parsed program instead of running it"),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Rewrites files in the canonical layout")
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Lists unformatted files and fails instead of rewriting them"),
+                )
+                .arg(Arg::with_name("FILE").multiple(true).required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Reports likely mistakes without running anything")
+                .arg(
+                    Arg::with_name("config")
+                        .long("config")
+                        .takes_value(true)
+                        .help("Rule settings; defaults to whiteye-lint.toml if present"),
+                )
+                .arg(Arg::with_name("FILE").multiple(true).required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("debug")
+                .about("Runs a file under the interactive debugger")
+                .arg(
+                    Arg::with_name("break")
+                        .short("b")
+                        .long("break")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Stops at LINE"),
+                )
+                .arg(
+                    Arg::with_name("dap")
+                        .long("dap")
+                        .conflicts_with_all(&["break", "FILE"])
+                        .help("Serves the Debug Adapter Protocol on stdio instead"),
+                )
+                .arg(Arg::with_name("FILE").required_unless("dap")),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about("Runs the test blocks in files, or in every .wy file under directories")
+                .arg(Arg::with_name("PATH").multiple(true).default_value(".")),
+        )
+        .subcommand(
+            SubCommand::with_name("lsp").about("Runs a Language Server Protocol server on stdio"),
+        );
 
     let matches = app.get_matches();
 
@@ -27,21 +124,74 @@ fn main() -> Result<()> {
 
     logger.init();
 
+    if let Some(matches) = matches.subcommand_matches("fmt") {
+        return fmt(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        return lint(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("test") {
+        return test(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("debug") {
+        return debug(matches);
+    }
+    if matches.subcommand_matches("lsp").is_some() {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        return Ok(lsp::run(stdin.lock(), stdout.lock())?);
+    }
+
     if let Some(path) = matches.value_of("FILE") {
         let input = fs::read_to_string(path)?;
 
         let mut machine = Machine::new();
+        machine.set_script_path(path);
+        machine.set_module_path(module_path_from_env());
+        let args = matches.values_of("ARGS").into_iter().flatten();
+        machine.set_args(args.map(String::from).collect());
 
         debug!("Raw: \n{}", input);
 
-        let parsed = parse(&input).unwrap_or_else(|e| panic!("{}", e));
-        debug!("AST: {:?}", parsed);
+        let parsed = match parse_with_spans(&input) {
+            Ok(parsed) => parsed,
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("{}:{}\n", path, e.render(&input));
+                }
+                process::exit(1);
+            }
+        };
 
-        for ast in parsed {
-            machine.run(ast).unwrap_or_else(|e| panic!("{}", e));
+        if matches.value_of("emit-ast") == Some("json") {
+            let program: Vec<_> = parsed.into_iter().map(|(ast, _)| ast).collect();
+            println!("{}", ast::to_json(&program));
+            return Ok(());
+        }
+
+        let profiler = Rc::new(RefCell::new(Profiler::new()));
+        if matches.is_present("profile") {
+            machine.set_debug_hook(profiler.clone());
+        } else if matches.is_present("trace") {
+            machine.set_debug_hook(Tracer::new(io::stderr()));
+        }
+
+        for (ast, span) in parsed {
+            if let Err(e) = machine.run_at(ast, span) {
+                eprintln!("{}:{}:{}: {}", path, span.line, span.column, e);
+                process::exit(1);
+            }
             debug!("machine state: {:?}", machine);
         }
 
+        if matches.is_present("profile") {
+            let profiler = profiler.borrow();
+            eprint!("{}", profiler.report());
+            if let Some(folded) = matches.value_of("folded") {
+                fs::write(folded, profiler.folded())?;
+            }
+        }
+
         /*
         if !input.is_empty() {
             eprintln!("parsing error, input remaining {:?}", input);
@@ -52,3 +202,161 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+fn fmt(matches: &ArgMatches) -> Result<()> {
+    let check = matches.is_present("check");
+    let mut failed = false;
+    for path in matches.values_of("FILE").unwrap() {
+        let input = fs::read_to_string(path)?;
+        let formatted = match formatter::format_source(&input) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("{}:{}\n", path, e.render(&input));
+                }
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == input {
+            continue;
+        }
+        if check {
+            println!("{}", path);
+            failed = true;
+        } else {
+            fs::write(path, formatted)?;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn test(matches: &ArgMatches) -> Result<()> {
+    let mut files = Vec::new();
+    for path in matches.values_of("PATH").unwrap() {
+        files.extend(testing::discover(Path::new(path))?);
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    let mut failures = Vec::new();
+    for file in &files {
+        let path = file.display().to_string();
+        let input = fs::read_to_string(file)?;
+        let results = match testing::run_tests_in(file, &input) {
+            Ok(results) => results,
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("{}:{}\n", path, e.render(&input));
+                }
+                failed += 1;
+                continue;
+            }
+        };
+        for result in results {
+            let status = if result.passed() { "ok" } else { "FAILED" };
+            println!("test {}: {} ... {}", path, result.name, status);
+            match result.failure {
+                None => passed += 1,
+                Some(failure) => {
+                    failed += 1;
+                    let heading = format!("---- {}: {} ----", path, result.name);
+                    failures.push(format!("{}\n{}", heading, failure.render(&path, &input)));
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:\n");
+        for failure in &failures {
+            println!("{}", failure);
+        }
+    }
+    let status = if failed == 0 { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        status, passed, failed
+    );
+    if failed > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn debug(matches: &ArgMatches) -> Result<()> {
+    if matches.is_present("dap") {
+        return Ok(dap::run(BufReader::new(io::stdin()), io::stdout())?);
+    }
+
+    let path = matches.value_of("FILE").unwrap();
+    let input = fs::read_to_string(path)?;
+    let program = match parse_with_spans(&input) {
+        Ok(program) => program,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}:{}\n", path, e.render(&input));
+            }
+            process::exit(1);
+        }
+    };
+
+    let mut debugger = Debugger::new(&input, BufReader::new(io::stdin()), io::stdout());
+    for line in matches.values_of("break").into_iter().flatten() {
+        debugger.add_breakpoint(line.parse()?);
+    }
+    let mut machine = Machine::new();
+    machine.set_script_path(path);
+    machine.set_module_path(module_path_from_env());
+    machine.set_debug_hook(debugger);
+    for (statement, span) in program {
+        match machine.run_at(statement, span) {
+            Ok(()) => {}
+            // The user quit.
+            Err(MachineError::Aborted) => return Ok(()),
+            Err(e) => {
+                eprintln!("{}:{}:{}: {}", path, span.line, span.column, e);
+                process::exit(1);
+            }
+        }
+    }
+    println!("program finished");
+    Ok(())
+}
+
+const LINT_CONFIG: &str = "whiteye-lint.toml";
+
+fn lint(matches: &ArgMatches) -> Result<()> {
+    let config = match matches.value_of("config") {
+        Some(path) => LintConfig::from_toml(&fs::read_to_string(path)?)?,
+        None if Path::new(LINT_CONFIG).exists() => {
+            LintConfig::from_toml(&fs::read_to_string(LINT_CONFIG)?)?
+        }
+        None => LintConfig::default(),
+    };
+
+    let mut failed = false;
+    for path in matches.values_of("FILE").unwrap() {
+        let input = fs::read_to_string(path)?;
+        match lint_source(&input, &config) {
+            Ok(warnings) => {
+                for w in &warnings {
+                    eprintln!("{}:{}\n", path, w.render(&input));
+                }
+                failed |= !warnings.is_empty();
+            }
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("{}:{}\n", path, e.render(&input));
+                }
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+    Ok(())
+}
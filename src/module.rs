@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::ModulePath;
+use crate::lexer::quote;
+use crate::machine::MachineError;
+use crate::value::Value;
+
+/// Environment variable holding extra directories to find `import name;` modules in,
+/// separated like `PATH`.
+pub const MODULE_PATH_VAR: &str = "WHITEYE_PATH";
+
+/// The directories listed in `WHITEYE_PATH`, for `Machine::set_module_path`.
+pub fn module_path_from_env() -> Vec<PathBuf> {
+    env::var_os(MODULE_PATH_VAR)
+        .map(|paths| env::split_paths(&paths).collect())
+        .unwrap_or_default()
+}
+
+/// A module that has been run: the values of its `pub` variables. A built-in module,
+/// such as `math`, has its name for a path and lists the built-in functions it
+/// offers as `alias.name(...)`; a module file cannot export functions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Module {
+    pub path: PathBuf,
+    pub exports: BTreeMap<String, Value>,
+    pub functions: BTreeSet<String>,
+}
+
+/// What a machine knows about modules: where to look for them, the ones already
+/// run, and the chain of modules being run, innermost last.
+#[derive(Debug, Default)]
+pub(crate) struct Modules {
+    /// The file being run, if known.
+    pub script: Option<PathBuf>,
+    pub search_path: Vec<PathBuf>,
+    pub loaded: HashMap<PathBuf, Rc<Module>>,
+    pub loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    /// Finds the file `module` refers to when imported from the innermost file being
+    /// run. A file path is relative to that file's directory; a module name is looked
+    /// for there and then in each search path directory. Built-in modules are not
+    /// files, and are left to the caller.
+    pub fn resolve(&self, module: &ModulePath) -> Result<PathBuf, MachineError> {
+        // Without a file to start from, the current directory stands in for its
+        // directory; joining it here shows it in `ModuleNotFound`.
+        let base = match self
+            .loading
+            .last()
+            .or(self.script.as_ref())
+            .and_then(|importer| importer.parent())
+        {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => env::current_dir().unwrap_or_default(),
+        };
+        let candidates: Vec<PathBuf> = match module {
+            ModulePath::File(path) => vec![base.join(path)],
+            ModulePath::Name(name) => {
+                let file = format!("{}.wy", name);
+                std::iter::once(base.as_path())
+                    .chain(self.search_path.iter().map(PathBuf::as_path))
+                    .map(|dir| dir.join(&file))
+                    .collect()
+            }
+        };
+        candidates
+            .iter()
+            .filter(|candidate| candidate.is_file())
+            .find_map(|candidate| fs::canonicalize(candidate).ok())
+            .ok_or_else(|| MachineError::ModuleNotFound {
+                module: match module {
+                    ModulePath::File(path) => quote(path),
+                    ModulePath::Name(name) => name.clone(),
+                },
+                searched: candidates,
+            })
+    }
+
+    /// The import chain that loading `path` would close into a cycle, if any.
+    pub fn cycle(&self, path: &Path) -> Option<Vec<PathBuf>> {
+        let files: Vec<&PathBuf> = self.script.iter().chain(&self.loading).collect();
+        let start = files.iter().position(|p| *p == path)?;
+        let mut chain: Vec<PathBuf> = files[start..].iter().map(|p| p.to_path_buf()).collect();
+        chain.push(path.to_path_buf());
+        Some(chain)
+    }
+}
@@ -1,50 +1,344 @@
 mod conditional_expression;
+mod error;
 mod expression;
 mod function_call;
+mod import;
 mod statement;
 mod variable;
 
+use std::cell::Cell;
+
 use nom::branch::alt;
-use nom::character::complete::multispace0;
-use nom::character::complete::space0;
-use nom::error::convert_error;
-use nom::error::VerboseError;
-use nom::sequence::delimited;
-use nom::Finish;
+use nom::error::context;
 use nom::IResult;
 
 use crate::ast::Ast;
+use crate::lexer::{tokenize_recovering, Span, Token, TokenKind};
+use expression::parse_expression;
 use function_call::parse_function_call;
+use import::parse_import;
 use statement::parse_statement;
 use variable::parse_variable_assignment;
 
-pub fn parse(input: &str) -> Result<Vec<Ast>, String> {
+pub use crate::lexer::{Comment, CommentKind};
+pub use error::{ParseError, SyntaxError, SyntaxErrorKind};
+
+pub type Tokens<'a> = &'a [Token];
+
+pub type ParseResult<'a, O> = IResult<Tokens<'a>, O, SyntaxError<'a>>;
+
+/// How deeply parentheses, function calls and unary operators may nest. The parser
+/// recurses once per level, as does everything that walks the tree it builds, so
+/// deeper source would overflow the stack.
+pub const MAX_NESTING: usize = 128;
+
+thread_local! {
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn parse(source: &str) -> Result<Vec<Ast>, Vec<ParseError>> {
+    let (result, _) = parse_with_comments(source)?;
+    Ok(result)
+}
+
+/// Parses `source` and also returns its comments, which are otherwise discarded.
+pub fn parse_with_comments(source: &str) -> Result<(Vec<Ast>, Vec<Comment>), Vec<ParseError>> {
+    let (tokens, comments, errors) = tokenize_recovering(source);
+    let result = with_lexer_errors(errors, parse_tokens(source, &tokens))?;
+    Ok((result, comments))
+}
+
+/// Parses a whole program. On a syntax error the parser skips to the next `;` or `}`
+/// and carries on, so that every error is reported in one pass.
+pub fn parse_tokens(source: &str, tokens: &[Token]) -> Result<Vec<Ast>, Vec<ParseError>> {
+    let (result, _) = parse_program(source, tokens, false)?;
+    Ok(result)
+}
+
+/// Like `parse`, but the program may end with an expression without `;`, which is
+/// returned separately.
+pub fn parse_with_tail_expression(
+    source: &str,
+) -> Result<(Vec<Ast>, Option<Ast>), Vec<ParseError>> {
+    let (tokens, _, errors) = tokenize_recovering(source);
+    with_lexer_errors(errors, parse_program(source, &tokens, true))
+}
+
+/// Like `parse`, but pairs each statement with its location in the source.
+pub fn parse_with_spans(source: &str) -> Result<Vec<(Ast, Span)>, Vec<ParseError>> {
+    let (tokens, _, errors) = tokenize_recovering(source);
+    let program = with_lexer_errors(errors, parse_tokens(source, &tokens))?;
+    let spans = statement_tokens(&tokens).map(statement_span);
+    Ok(program.into_iter().zip(spans).collect())
+}
+
+/// Splits the tokens of a successfully parsed program into those of each statement,
+/// each ending with its `;` or, for a test block, its `}`, so that tools can locate
+/// statements in the source.
+pub fn statement_tokens(tokens: &[Token]) -> impl Iterator<Item = &[Token]> {
+    let mut rest = tokens;
+    std::iter::from_fn(move || {
+        let mut depth = 0;
+        let end = rest.iter().position(|t| {
+            match t.kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth -= 1,
+                _ => {}
+            }
+            depth == 0 && matches!(t.kind, TokenKind::Semicolon | TokenKind::RBrace)
+        });
+        let end = end.map_or(rest.len(), |i| i + 1);
+        let (statement, remain) = rest.split_at(end);
+        rest = remain;
+        Some(statement).filter(|s| !s.is_empty())
+    })
+}
+
+/// The location of a statement split off by `statement_tokens`: from its first token
+/// to its end.
+pub fn statement_span(tokens: &[Token]) -> Span {
+    let (first, last) = (&tokens[0], &tokens[tokens.len() - 1]);
+    Span {
+        end: last.span.end,
+        ..first.span
+    }
+}
+
+/// The tokens between the braces of a test block, as split off by `statement_tokens`.
+pub fn test_body_tokens(tokens: &[Token]) -> &[Token] {
+    &tokens[3..tokens.len() - 1]
+}
+
+fn parse_program(
+    source: &str,
+    tokens: &[Token],
+    allow_tail: bool,
+) -> Result<(Vec<Ast>, Option<Ast>), Vec<ParseError>> {
     let mut result: Vec<Ast> = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
 
-    let mut looping = true;
+    let mut input = tokens;
+    while !input.is_empty() {
+        if allow_tail {
+            if let Ok((remain, tail)) = parse_expression(input) {
+                if remain.is_empty() {
+                    return if errors.is_empty() {
+                        Ok((result, Some(tail)))
+                    } else {
+                        Err(errors)
+                    };
+                }
+            }
+        }
+
+        if let Some((remain, test)) = parse_test(source, input) {
+            match test {
+                Ok(test) => result.push(test),
+                Err(test_errors) => errors.extend(test_errors),
+            }
+            input = remain;
+            continue;
+        }
 
-    let mut input = input;
-    while looping {
-        match root_parser(input).finish() {
+        match root_parser(input) {
             Ok((remain, parsed)) => {
                 result.push(parsed);
-                input = remain;
-                looping = !remain.is_empty();
+                input = match token(TokenKind::Semicolon)(remain) {
+                    Ok((remain, _)) => remain,
+                    Err(_) => {
+                        let last = &input[input.len() - remain.len() - 1];
+                        errors.push(missing_terminator(source, last, remain));
+                        // A statement on the next line most likely just lacks its `;`, so
+                        // carry on as if it had been there. Otherwise skip the rest.
+                        match remain.first() {
+                            Some(next) if next.span.line > last.span.line => remain,
+                            _ => synchronize(remain),
+                        }
+                    }
+                };
             }
-            Err(e) => return Err(convert_error(input, e)),
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                errors.push(e.to_parse_error(end_of_input(source)));
+                input = synchronize(input);
+            }
+            Err(nom::Err::Incomplete(_)) => unreachable!(),
         };
     }
-    Ok(result)
+
+    if errors.is_empty() {
+        Ok((result, None))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses `test "name" { ... }` if `input` starts with one. `test` is only a keyword
+/// there, so it stays usable as a name.
+fn parse_test<'a>(
+    source: &str,
+    input: Tokens<'a>,
+) -> Option<(Tokens<'a>, Result<Ast, Vec<ParseError>>)> {
+    let name = match input {
+        [Token {
+            kind: TokenKind::Identifier(keyword),
+            ..
+        }, Token {
+            kind: TokenKind::Str(name),
+            ..
+        }, Token {
+            kind: TokenKind::LBrace,
+            ..
+        }, ..]
+            if keyword == "test" =>
+        {
+            name.clone()
+        }
+        _ => return None,
+    };
+    let open = &input[2];
+    let close = match input[3..].iter().position(|t| t.kind == TokenKind::RBrace) {
+        Some(i) => i + 3,
+        None => {
+            let error = ParseError {
+                span: open.span,
+                expected: vec!["`}`".to_string()],
+                found: "end of input".to_string(),
+                message: "unclosed `{` of test block".to_string(),
+            };
+            return Some((&[], Err(vec![error])));
+        }
+    };
+    // Nothing else in the grammar uses braces, so another `{` opens a nested test.
+    let body = &input[3..close];
+    if let Some(nested) = body.iter().find(|t| t.kind == TokenKind::LBrace) {
+        let error = ParseError {
+            span: nested.span,
+            expected: Vec::new(),
+            found: nested.kind.to_string(),
+            message: "test blocks cannot be nested".to_string(),
+        };
+        return Some((&input[close + 1..], Err(vec![error])));
+    }
+    let body = parse_program(source, body, false).map(|(body, _)| body);
+    Some((
+        &input[close + 1..],
+        body.map(|body| Ast::Test { name, body }),
+    ))
 }
 
-pub fn root_parser(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    delimited(
-        space0,
+fn with_lexer_errors<T>(
+    mut errors: Vec<ParseError>,
+    result: Result<T, Vec<ParseError>>,
+) -> Result<T, Vec<ParseError>> {
+    match result {
+        Ok(result) if errors.is_empty() => Ok(result),
+        Ok(_) => Err(errors),
+        Err(parse_errors) => {
+            errors.extend(parse_errors);
+            errors.sort_by_key(|e| e.span.start);
+            Err(errors)
+        }
+    }
+}
+
+pub fn root_parser(input: Tokens) -> ParseResult<Ast> {
+    context(
+        "statement",
         alt((
+            parse_import,
             parse_statement,
             parse_variable_assignment,
             parse_function_call,
         )),
-        multispace0,
     )(input)
 }
+
+/// Runs `parser` `levels` deeper than the current nesting, failing at `at` instead if
+/// that is over `MAX_NESTING`.
+fn nested<'a, O>(
+    at: Tokens<'a>,
+    levels: usize,
+    parser: impl FnOnce() -> ParseResult<'a, O>,
+) -> ParseResult<'a, O> {
+    let depth = NESTING.with(Cell::get) + levels;
+    if depth > MAX_NESTING {
+        return Err(nom::Err::Failure(SyntaxError {
+            errors: vec![(at, SyntaxErrorKind::TooDeep(MAX_NESTING))],
+        }));
+    }
+    NESTING.with(|nesting| nesting.set(depth));
+    let result = parser();
+    NESTING.with(|nesting| nesting.set(depth - levels));
+    result
+}
+
+/// Matches a single token of the given kind.
+fn token<'a>(kind: TokenKind) -> impl Fn(Tokens<'a>) -> ParseResult<'a, &'a Token> {
+    move |input: Tokens<'a>| match input.split_first() {
+        Some((t, remain)) if t.kind == kind => Ok((remain, t)),
+        _ => Err(nom::Err::Error(SyntaxError::expected(
+            input,
+            format!("`{}`", kind.symbol()),
+        ))),
+    }
+}
+
+/// Matches the token closing `open`, reporting where `open` was if it is missing.
+fn closing<'a>(
+    open: &'a Token,
+    kind: TokenKind,
+) -> impl Fn(Tokens<'a>) -> ParseResult<'a, &'a Token> {
+    move |input: Tokens<'a>| {
+        token(kind.clone())(input).map_err(|e| {
+            e.map(|mut e| {
+                e.errors.push((
+                    input,
+                    SyntaxErrorKind::Unclosed {
+                        delimiter: open.kind.symbol(),
+                        open: open.span,
+                    },
+                ));
+                e
+            })
+        })
+    }
+}
+
+fn synchronize(input: Tokens) -> Tokens {
+    match input
+        .iter()
+        .position(|t| matches!(t.kind, TokenKind::Semicolon | TokenKind::RBrace))
+    {
+        Some(i) => &input[i + 1..],
+        None => &[],
+    }
+}
+
+fn missing_terminator(source: &str, last: &Token, remain: Tokens) -> ParseError {
+    // Point just past the statement rather than at whatever follows it.
+    let length = source[last.span.start..last.span.end].chars().count();
+    ParseError {
+        span: Span {
+            start: last.span.end,
+            end: last.span.end,
+            line: last.span.line,
+            column: last.span.column + length,
+        },
+        expected: vec!["`;`".to_string()],
+        found: remain
+            .first()
+            .map_or("end of input".to_string(), |t| t.kind.to_string()),
+        message: "expected `;` after statement".to_string(),
+    }
+}
+
+fn end_of_input(source: &str) -> Span {
+    let line = source.matches('\n').count() + 1;
+    let column = source.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    Span {
+        start: source.len(),
+        end: source.len(),
+        line,
+        column,
+    }
+}
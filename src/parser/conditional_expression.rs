@@ -1,43 +1,51 @@
-use nom::bytes::complete::is_a;
-use nom::error::VerboseError;
+use nom::branch::alt;
 use nom::multi::many0;
 use nom::sequence::tuple;
-use nom::IResult;
 
 use crate::ast::{Ast, ComparisonOpKind, LogicalOpKind};
+use crate::lexer::{Token, TokenKind};
 
 use super::expression::parse_add_sub;
+use super::{token, ParseResult, SyntaxError, Tokens};
 
-pub fn parse_conditional_expr(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    parse_and_or(input)
-}
-
-fn parse_and_or(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, left_expr) = parse_comparison_expr(input)?;
-    let (input, exprs) = many0(tuple((is_a("&|"), parse_comparison_expr)))(input)?;
+/// Parses the rest of a conditional expression whose first operand, `left`, has
+/// already been parsed, or returns `left` if no comparison operator follows it.
+pub fn parse_conditional_expr(input: Tokens, left: Ast) -> ParseResult<Ast> {
+    if parse_comparison_operator(input).is_err() {
+        return Ok((input, left));
+    }
+    let (input, left_expr) = parse_comparison_rest(input, left)?;
+    let (input, exprs) = many0(tuple((
+        alt((token(TokenKind::And), token(TokenKind::Or))),
+        parse_comparison_expr,
+    )))(input)?;
     Ok((input, parse_logical_expr(left_expr, exprs)))
 }
 
-fn parse_logical_expr(left_expr: Ast, exprs: Vec<(&str, Ast)>) -> Ast {
+fn parse_logical_expr(left_expr: Ast, exprs: Vec<(&Token, Ast)>) -> Ast {
     exprs
         .into_iter()
         .fold(left_expr, |left_expr, exprs| Ast::LogicalExpr {
             left: Box::new(left_expr),
-            operator: parse_logical_oprator(exprs.0),
+            operator: parse_logical_oprator(&exprs.0.kind),
             right: Box::new(exprs.1),
         })
 }
 
-fn parse_logical_oprator(input: &str) -> LogicalOpKind {
+fn parse_logical_oprator(input: &TokenKind) -> LogicalOpKind {
     match input {
-        "&&" => LogicalOpKind::LAnd,
-        "||" => LogicalOpKind::LOr,
+        TokenKind::And => LogicalOpKind::LAnd,
+        TokenKind::Or => LogicalOpKind::LOr,
         _ => panic!("Unknown Operation"),
     }
 }
 
-fn parse_comparison_expr(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
+fn parse_comparison_expr(input: Tokens) -> ParseResult<Ast> {
     let (input, left_expr) = parse_add_sub(input)?;
+    parse_comparison_rest(input, left_expr)
+}
+
+fn parse_comparison_rest(input: Tokens, left_expr: Ast) -> ParseResult<Ast> {
     let (input, comparison_op) = parse_comparison_operator(input)?;
     let (input, right_expr) = parse_add_sub(input)?;
     Ok((
@@ -50,16 +58,20 @@ fn parse_comparison_expr(input: &str) -> IResult<&str, Ast, VerboseError<&str>>
     ))
 }
 
-fn parse_comparison_operator(input: &str) -> IResult<&str, ComparisonOpKind, VerboseError<&str>> {
-    let (input, comparison_op_str) = is_a("=><!")(input)?;
-    let comparison_op = match comparison_op_str {
-        "==" => ComparisonOpKind::CEqual,
-        "!=" => ComparisonOpKind::CNot,
-        ">" => ComparisonOpKind::CGreater,
-        "<" => ComparisonOpKind::CLess,
-        ">=" => ComparisonOpKind::CGreaterEqual,
-        "<=" => ComparisonOpKind::CLessEqual,
-        _ => panic!("Unknown Operation"),
+fn parse_comparison_operator(input: Tokens) -> ParseResult<ComparisonOpKind> {
+    let comparison_op = match input.first().map(|t| &t.kind) {
+        Some(TokenKind::Equal) => ComparisonOpKind::CEqual,
+        Some(TokenKind::NotEqual) => ComparisonOpKind::CNot,
+        Some(TokenKind::Greater) => ComparisonOpKind::CGreater,
+        Some(TokenKind::Less) => ComparisonOpKind::CLess,
+        Some(TokenKind::GreaterEqual) => ComparisonOpKind::CGreaterEqual,
+        Some(TokenKind::LessEqual) => ComparisonOpKind::CLessEqual,
+        _ => {
+            return Err(nom::Err::Error(SyntaxError::expected(
+                input,
+                "comparison operator",
+            )))
+        }
     };
-    Ok((input, comparison_op))
+    Ok((&input[1..], comparison_op))
 }
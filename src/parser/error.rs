@@ -0,0 +1,159 @@
+use std::fmt;
+
+use nom::error::{ContextError, ErrorKind, ParseError as NomParseError};
+
+use super::Tokens;
+use crate::lexer::Span;
+
+/// A syntax error found while tokenizing or parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub span: Span,
+    /// Descriptions of what would have been accepted, e.g. "`;`" or "identifier".
+    pub expected: Vec<String>,
+    /// Description of what was there instead, e.g. "identifier `x`" or "end of input".
+    pub found: String,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Formats the error together with the offending source line.
+    pub fn render(&self, source: &str) -> String {
+        format!("{}\n{}", self, self.span.excerpt(source))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.line, self.span.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxErrorKind {
+    /// What the parser was looking for, e.g. "`;`" or "identifier".
+    Expected(Vec<String>),
+    /// The error happened while looking for the token closing `delimiter`, which was opened at `open`.
+    Unclosed {
+        delimiter: &'static str,
+        open: Span,
+    },
+    Context(&'static str),
+    /// Parentheses, calls and unary operators nest deeper than this limit.
+    TooDeep(usize),
+    Nom(ErrorKind),
+}
+
+/// Error type of the token parsers, the token-stream counterpart of nom's `VerboseError`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError<'a> {
+    pub errors: Vec<(Tokens<'a>, SyntaxErrorKind)>,
+}
+
+impl<'a> SyntaxError<'a> {
+    pub fn expected(input: Tokens<'a>, expected: impl Into<String>) -> Self {
+        SyntaxError {
+            errors: vec![(input, SyntaxErrorKind::Expected(vec![expected.into()]))],
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.errors.first().map_or(0, |(input, _)| input.len())
+    }
+
+    /// Converts the innermost error into a `ParseError`. `end_of_input` locates errors at the end of the stream.
+    pub fn to_parse_error(&self, end_of_input: Span) -> ParseError {
+        let (input, expected) = match self.errors.first() {
+            Some((input, SyntaxErrorKind::Expected(expected))) => (*input, expected.clone()),
+            Some((input, _)) => (*input, Vec::new()),
+            None => (&[][..], Vec::new()),
+        };
+        let (span, found) = match input.first() {
+            Some(token) => (token.span, token.kind.to_string()),
+            None => (end_of_input, "end of input".to_string()),
+        };
+
+        let unclosed = self.errors.iter().find_map(|(_, kind)| match kind {
+            SyntaxErrorKind::Unclosed { delimiter, open } => Some((*delimiter, *open)),
+            _ => None,
+        });
+        let too_deep = self.errors.iter().find_map(|(_, kind)| match kind {
+            SyntaxErrorKind::TooDeep(limit) => Some(*limit),
+            _ => None,
+        });
+        let message = match (too_deep, unclosed) {
+            (Some(limit), _) => format!("expression nested more than {} levels deep", limit),
+            (_, Some((delimiter, open))) if !expected.is_empty() => format!(
+                "expected {} to close `{}` opened at {}:{}",
+                expected.join(" or "),
+                delimiter,
+                open.line,
+                open.column
+            ),
+            _ if !expected.is_empty() => {
+                format!("expected {}, found {}", expected.join(" or "), found)
+            }
+            _ => format!("unexpected {}", found),
+        };
+
+        ParseError {
+            span,
+            expected,
+            found,
+            message,
+        }
+    }
+}
+
+impl<'a> NomParseError<Tokens<'a>> for SyntaxError<'a> {
+    fn from_error_kind(input: Tokens<'a>, kind: ErrorKind) -> Self {
+        SyntaxError {
+            errors: vec![(input, SyntaxErrorKind::Nom(kind))],
+        }
+    }
+
+    fn append(input: Tokens<'a>, kind: ErrorKind, mut other: Self) -> Self {
+        other.errors.push((input, SyntaxErrorKind::Nom(kind)));
+        other
+    }
+
+    // Keep whichever alternative got furthest, merging what was expected when they tie.
+    fn or(self, other: Self) -> Self {
+        if self.remaining() < other.remaining() {
+            return self;
+        }
+        if self.remaining() > other.remaining() {
+            return other;
+        }
+        match (self.errors.first(), other.errors.first()) {
+            (
+                Some((input, SyntaxErrorKind::Expected(a))),
+                Some((_, SyntaxErrorKind::Expected(b))),
+            ) => {
+                let mut expected = a.clone();
+                for e in b {
+                    if !expected.contains(e) {
+                        expected.push(e.clone());
+                    }
+                }
+                SyntaxError {
+                    errors: vec![(input, SyntaxErrorKind::Expected(expected))],
+                }
+            }
+            _ => other,
+        }
+    }
+}
+
+impl<'a> ContextError<Tokens<'a>> for SyntaxError<'a> {
+    fn add_context(input: Tokens<'a>, ctx: &'static str, mut other: Self) -> Self {
+        other.errors.push((input, SyntaxErrorKind::Context(ctx)));
+        other
+    }
+}
@@ -1,112 +1,114 @@
 use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{digit1, one_of, space0};
-use nom::error::VerboseError;
+use nom::combinator::cut;
 use nom::multi::many0;
-use nom::number::complete::recognize_float;
-use nom::sequence::{delimited, tuple};
-use nom::IResult;
+use nom::sequence::{terminated, tuple};
 
-use super::variable::parse_variable_name;
+use super::conditional_expression::parse_conditional_expr;
+use super::function_call::parse_function_call;
+use super::variable::parse_qualified_name;
+use super::{closing, nested, token, ParseResult, SyntaxError, Tokens};
 use crate::ast::{Ast, ExprOpKind, UnaryOpKind};
+use crate::lexer::{Token, TokenKind};
 use crate::value::Value;
 
-pub fn parse_add_sub(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
+// The first operand is parsed once whether or not a comparison follows; trying each
+// alternative from the start would parse nested calls an exponential number of times.
+pub fn parse_expression(input: Tokens) -> ParseResult<Ast> {
+    let (input, left) = parse_add_sub(input)?;
+    parse_conditional_expr(input, left)
+}
+
+pub fn parse_add_sub(input: Tokens) -> ParseResult<Ast> {
     let (input, left_expr) = parse_mul_div(input)?;
-    let (input, exprs) = many0(tuple((one_of("+-"), parse_mul_div)))(input)?;
+    let (input, exprs) = many0(tuple((
+        alt((token(TokenKind::Plus), token(TokenKind::Minus))),
+        parse_mul_div,
+    )))(input)?;
     Ok((input, parse_math_expr(left_expr, exprs)))
 }
 
-fn parse_mul_div(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
+fn parse_mul_div(input: Tokens) -> ParseResult<Ast> {
     let (input, left_expr) = parse_unary(input)?;
-    let (input, exprs) = many0(tuple((one_of("*/"), parse_mul_div)))(input)?;
+    let (input, exprs) = many0(tuple((
+        alt((token(TokenKind::Star), token(TokenKind::Slash))),
+        parse_unary,
+    )))(input)?;
     Ok((input, parse_math_expr(left_expr, exprs)))
 }
 
-fn parse_math_expr(left_expr: Ast, exprs: Vec<(char, Ast)>) -> Ast {
+fn parse_math_expr(left_expr: Ast, exprs: Vec<(&Token, Ast)>) -> Ast {
     exprs
         .into_iter()
         .fold(left_expr, |left_expr, exprs| Ast::Expr {
             left: Box::new(left_expr),
-            operator: parse_expr_operator(exprs.0),
+            operator: parse_expr_operator(&exprs.0.kind),
             right: Box::new(exprs.1),
         })
 }
 
-fn parse_expr_operator(expr_op_char: char) -> ExprOpKind {
-    match expr_op_char {
-        '+' => ExprOpKind::EAdd,
-        '-' => ExprOpKind::ESub,
-        '*' => ExprOpKind::EMul,
-        '/' => ExprOpKind::EDiv,
+fn parse_expr_operator(expr_op: &TokenKind) -> ExprOpKind {
+    match expr_op {
+        TokenKind::Plus => ExprOpKind::EAdd,
+        TokenKind::Minus => ExprOpKind::ESub,
+        TokenKind::Star => ExprOpKind::EMul,
+        TokenKind::Slash => ExprOpKind::EDiv,
         _ => panic!("Unknown Operation"),
     }
 }
 
-fn parse_unary(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, unary_op_chars) = many0(tuple((space0, one_of("+-"))))(input)?;
-    let (input, expr) = parse_par_float_int_var(input)?;
-    Ok((input, parse_monomial(unary_op_chars, expr)))
+fn parse_unary(input: Tokens) -> ParseResult<Ast> {
+    let (rest, unary_ops) = many0(alt((token(TokenKind::Plus), token(TokenKind::Minus))))(input)?;
+    let (input, expr) = nested(input, unary_ops.len(), || parse_par_float_int_var(rest))?;
+    Ok((input, parse_monomial(unary_ops, expr)))
 }
 
-fn parse_monomial(unary_op_chars: Vec<(&str, char)>, expr: Ast) -> Ast {
-    unary_op_chars
+fn parse_monomial(unary_ops: Vec<&Token>, expr: Ast) -> Ast {
+    unary_ops
         .into_iter()
-        .fold(expr, |expr, unary_op_char| Ast::Monomial {
-            operator: parse_unary_operator(unary_op_char.1),
+        .rev()
+        .fold(expr, |expr, unary_op| Ast::Monomial {
+            operator: parse_unary_operator(&unary_op.kind),
             expr: Box::new(expr),
         })
 }
 
-fn parse_unary_operator(unary_op_char: char) -> UnaryOpKind {
-    match unary_op_char {
-        '+' => UnaryOpKind::UPlus,
-        '-' => UnaryOpKind::UMinus,
+fn parse_unary_operator(unary_op: &TokenKind) -> UnaryOpKind {
+    match unary_op {
+        TokenKind::Plus => UnaryOpKind::UPlus,
+        TokenKind::Minus => UnaryOpKind::UMinus,
         _ => panic!("Unknown Operation"),
     }
 }
 
-fn parse_par_float_int_var(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    delimited(
-        space0,
-        alt((
-            parse_parentheses,
-            parse_float,
-            parse_integer,
-            parse_bool,
-            parse_variable,
-        )),
-        space0,
-    )(input)
-}
-
-fn parse_parentheses(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    delimited(tag("("), delimited(space0, parse_add_sub, space0), tag(")"))(input)
+fn parse_par_float_int_var(input: Tokens) -> ParseResult<Ast> {
+    alt((
+        parse_parentheses,
+        parse_literal,
+        parse_function_call,
+        parse_variable,
+    ))(input)
 }
 
-fn parse_integer(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, value_str) = digit1(input)?;
-    let value = value_str.parse::<isize>().unwrap();
-    Ok((input, Ast::Literal(Value::Integer(value))))
-}
-
-fn parse_float(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (check_input, _) = digit1(input)?;
-    let _ = tag(".")(check_input)?;
-
-    digit1(input)?;
-    let (input, value_str) = recognize_float(input)?;
-    let value = value_str.parse::<f64>().unwrap();
-    Ok((input, Ast::Literal(Value::Float(value))))
+fn parse_parentheses(input: Tokens) -> ParseResult<Ast> {
+    let (rest, open) = token(TokenKind::LParen)(input)?;
+    nested(input, 1, || {
+        cut(terminated(parse_add_sub, closing(open, TokenKind::RParen)))(rest)
+    })
 }
 
-fn parse_bool(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, truth_value_str) = alt((tag("true"), tag("false")))(input)?;
-    let truth_value = truth_value_str.parse::<bool>().unwrap();
-    Ok((input, Ast::Literal(Value::Bool(truth_value))))
+fn parse_literal(input: Tokens) -> ParseResult<Ast> {
+    let value = match input.first().map(|t| &t.kind) {
+        Some(TokenKind::Integer(v)) => Value::Integer(*v),
+        Some(TokenKind::Float(v)) => Value::Float(*v),
+        Some(TokenKind::True) => Value::Bool(true),
+        Some(TokenKind::False) => Value::Bool(false),
+        Some(TokenKind::Str(v)) => Value::String(v.clone()),
+        _ => return Err(nom::Err::Error(SyntaxError::expected(input, "literal"))),
+    };
+    Ok((&input[1..], Ast::Literal(value)))
 }
 
-fn parse_variable(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, v_name) = parse_variable_name(input)?;
-    Ok((input, Ast::Variable(v_name.into())))
+fn parse_variable(input: Tokens) -> ParseResult<Ast> {
+    let (input, v_name) = parse_qualified_name(input)?;
+    Ok((input, Ast::Variable(v_name)))
 }
@@ -1,22 +1,27 @@
-use nom::bytes::complete::is_a;
-use nom::bytes::complete::tag;
-use nom::error::VerboseError;
-use nom::sequence::delimited;
-use nom::IResult;
+use nom::combinator::cut;
+use nom::multi::separated_list0;
+use nom::sequence::terminated;
 
+use super::expression::parse_expression;
+use super::variable::parse_qualified_name;
+use super::{closing, nested, token, ParseResult, Tokens};
 use crate::ast::Ast;
+use crate::lexer::TokenKind;
 
-use super::expression::parse_add_sub;
-
-pub fn parse_function_call(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, function_name) =
-        is_a("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890_")(input)?;
-    let (input, function_argument) = delimited(tag("("), parse_add_sub, tag(")"))(input)?;
+pub fn parse_function_call(input: Tokens) -> ParseResult<Ast> {
+    let (input, function_name) = parse_qualified_name(input)?;
+    let (rest, open) = token(TokenKind::LParen)(input)?;
+    let (input, function_arguments) = nested(input, 1, || {
+        cut(terminated(
+            separated_list0(token(TokenKind::Comma), parse_expression),
+            closing(open, TokenKind::RParen),
+        ))(rest)
+    })?;
     Ok((
         input,
         Ast::FunctionCall {
-            name: function_name.to_string(),
-            argument: Box::new(function_argument),
+            name: function_name,
+            arguments: function_arguments,
         },
     ))
 }
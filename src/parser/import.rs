@@ -0,0 +1,35 @@
+use nom::combinator::cut;
+
+use super::variable::parse_variable_name;
+use super::{ParseResult, SyntaxError, Tokens};
+use crate::ast::{Ast, ModulePath};
+use crate::lexer::{Token, TokenKind};
+
+/// Parses `import "path.wy";` or `import name;`, either optionally followed by
+/// `as alias`. `import` and `as` are only keywords here, so they stay usable as names.
+pub fn parse_import(input: Tokens) -> ParseResult<Ast> {
+    let module = match input {
+        [Token {
+            kind: TokenKind::Identifier(keyword),
+            ..
+        }, target, ..]
+            if keyword == "import" =>
+        {
+            match &target.kind {
+                TokenKind::Str(path) => ModulePath::File(path.clone()),
+                TokenKind::Identifier(name) => ModulePath::Name(name.clone()),
+                _ => return Err(nom::Err::Error(SyntaxError::expected(input, "import"))),
+            }
+        }
+        _ => return Err(nom::Err::Error(SyntaxError::expected(input, "import"))),
+    };
+    let input = &input[2..];
+    let (input, alias) = match input.first().map(|t| &t.kind) {
+        Some(TokenKind::Identifier(word)) if word == "as" => {
+            let (input, alias) = cut(parse_variable_name)(&input[1..])?;
+            (input, Some(alias))
+        }
+        _ => (input, None),
+    };
+    Ok((input, Ast::Import { module, alias }))
+}
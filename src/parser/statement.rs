@@ -1,9 +1,7 @@
-use nom::error::VerboseError;
-use nom::IResult;
-
 use super::variable::parse_variable_declaration;
+use super::{ParseResult, Tokens};
 use crate::ast::Ast;
 
-pub fn parse_statement(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
+pub fn parse_statement(input: Tokens) -> ParseResult<Ast> {
     parse_variable_declaration(input)
 }
@@ -1,88 +1,113 @@
-use nom::branch::alt;
-use nom::bytes::complete::{is_a, tag};
-use nom::character::complete::{alphanumeric0, multispace0};
-use nom::error::VerboseError;
-use nom::sequence::delimited;
-use nom::IResult;
+use nom::combinator::cut;
+use nom::sequence::tuple;
 
-use super::conditional_expression::parse_conditional_expr;
-use super::expression::parse_add_sub;
+use super::expression::parse_expression;
+use super::{token, ParseResult, SyntaxError, Tokens};
 
 use crate::ast::{AssignmentOpKind, Ast, ValueType};
+use crate::lexer::{Token, TokenKind};
 
-pub fn parse_variable_declaration(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
-    let (input, variable_name) = delimited(
-        tag("let"),
-        delimited(multispace0, parse_variable_name, multispace0),
-        tag(":"),
-    )(input)?;
-    let (input, variable_type) = parse_variable_type(input)?;
-    let (input, _) = tag("=")(input)?;
-    let (input, _) = multispace0(input)?;
-    let (input, variable_expr) = alt((parse_conditional_expr, parse_add_sub))(input)?;
+pub fn parse_variable_declaration(input: Tokens) -> ParseResult<Ast> {
+    let (input, public) = match input {
+        [Token {
+            kind: TokenKind::Identifier(word),
+            ..
+        }, Token {
+            kind: TokenKind::Let,
+            ..
+        }, ..]
+            if word == "pub" =>
+        {
+            (&input[1..], true)
+        }
+        _ => (input, false),
+    };
+    let (input, _) = token(TokenKind::Let)(input)?;
+    let (input, (variable_name, _, variable_type, _, variable_expr)) = cut(tuple((
+        parse_variable_name,
+        token(TokenKind::Colon),
+        parse_variable_type,
+        token(TokenKind::Assign),
+        parse_expression,
+    )))(input)?;
     Ok((
         input,
         Ast::VariableDeclaration {
-            name: variable_name.to_string(),
+            name: variable_name,
             value_type: variable_type,
             expr: Box::new(variable_expr),
+            public,
         },
     ))
 }
 
-pub fn parse_variable_assignment(input: &str) -> IResult<&str, Ast, VerboseError<&str>> {
+pub fn parse_variable_assignment(input: Tokens) -> ParseResult<Ast> {
     let (input, variable_name) = parse_variable_name(input)?;
-    let (input, assignment_op) =
-        delimited(multispace0, parse_assignment_operator, multispace0)(input)?;
-    let (input, variable_expr) = alt((parse_conditional_expr, parse_add_sub))(input)?;
+    let (input, assignment_op) = parse_assignment_operator(input)?;
+    let (input, variable_expr) = cut(parse_expression)(input)?;
     Ok((
         input,
         Ast::VariableAssignment {
-            name: variable_name.to_string(),
+            name: variable_name,
             operator: assignment_op,
             expr: Box::new(variable_expr),
         },
     ))
 }
 
-pub fn parse_variable_name(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
-    let (input, variable_name) =
-        is_a("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890_")(input)?;
-    match is_a::<&str, &str, VerboseError<&str>>(
-        "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_",
-    )(variable_name)
-    {
-        Ok((_, _)) => Ok((input, variable_name)),
-        Err(e) => Err(e),
+pub fn parse_variable_name(input: Tokens) -> ParseResult<String> {
+    match input.split_first() {
+        Some((token, remain)) => match &token.kind {
+            TokenKind::Identifier(name) => Ok((remain, name.clone())),
+            _ => Err(nom::Err::Error(SyntaxError::expected(input, "identifier"))),
+        },
+        None => Err(nom::Err::Error(SyntaxError::expected(input, "identifier"))),
     }
 }
 
-pub fn parse_variable_type(input: &str) -> IResult<&str, ValueType, VerboseError<&str>> {
-    let (input, variable_type_str) = delimited(multispace0, alphanumeric0, multispace0)(input)?;
-    Ok((
-        input,
-        match variable_type_str {
-            "int" => ValueType::Integer,
-            "float" => ValueType::Float,
-            "bool" => ValueType::Bool,
-            _ => panic!("Unknown VariableDeclaration type"),
-        },
-    ))
+/// A name that may be qualified by a module: `x` or `m.x`.
+pub fn parse_qualified_name(input: Tokens) -> ParseResult<String> {
+    let (input, name) = parse_variable_name(input)?;
+    match token(TokenKind::Dot)(input) {
+        Ok((remain, _)) => {
+            let (remain, member) = cut(parse_variable_name)(remain)?;
+            Ok((remain, format!("{}.{}", name, member)))
+        }
+        Err(_) => Ok((input, name)),
+    }
 }
 
-pub fn parse_assignment_operator(
-    input: &str,
-) -> IResult<&str, AssignmentOpKind, VerboseError<&str>> {
-    let (input, assignment_op) = is_a("=+-*/")(input)?;
-    Ok((
-        input,
-        match assignment_op {
-            "=" => AssignmentOpKind::AEqual,
-            "+=" => AssignmentOpKind::AAdd,
-            "-=" => AssignmentOpKind::ASub,
-            "*=" => AssignmentOpKind::AMul,
-            "/=" => AssignmentOpKind::AMul,
-            _ => panic!("Unknown Assignment Operation"),
-        },
-    ))
+pub fn parse_variable_type(input: Tokens) -> ParseResult<ValueType> {
+    let (remain, variable_type_str) = parse_variable_name(input)?;
+    let variable_type = match variable_type_str.as_str() {
+        "int" => ValueType::Integer,
+        "float" => ValueType::Float,
+        "bool" => ValueType::Bool,
+        "string" => ValueType::String,
+        "list" => ValueType::List,
+        _ => {
+            return Err(nom::Err::Error(SyntaxError::expected(
+                input,
+                "type `int`, `float`, `bool`, `string` or `list`",
+            )))
+        }
+    };
+    Ok((remain, variable_type))
+}
+
+pub fn parse_assignment_operator(input: Tokens) -> ParseResult<AssignmentOpKind> {
+    let assignment_op = match input.first().map(|t| &t.kind) {
+        Some(TokenKind::Assign) => AssignmentOpKind::AEqual,
+        Some(TokenKind::PlusAssign) => AssignmentOpKind::AAdd,
+        Some(TokenKind::MinusAssign) => AssignmentOpKind::ASub,
+        Some(TokenKind::StarAssign) => AssignmentOpKind::AMul,
+        Some(TokenKind::SlashAssign) => AssignmentOpKind::ADiv,
+        _ => {
+            return Err(nom::Err::Error(SyntaxError::expected(
+                input,
+                "assignment operator",
+            )))
+        }
+    };
+    Ok((&input[1..], assignment_op))
 }
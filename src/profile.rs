@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::ast::Ast;
+use crate::formatter::format_statement;
+use crate::machine::{DebugHook, Machine};
+use crate::sandbox::Progress;
+use crate::value::Value;
+
+/// Where a statement is and what it says; `line` and `column` are 0 when it was run
+/// without a location.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct StatementKey {
+    line: usize,
+    column: usize,
+    text: String,
+}
+
+impl StatementKey {
+    fn new(machine: &Machine, statement: &Ast) -> Self {
+        let span = machine.location().unwrap_or_default();
+        StatementKey {
+            line: span.line,
+            column: span.column,
+            text: format_statement(statement),
+        }
+    }
+
+    /// The statement as a frame of a folded stack, where `;` separates frames.
+    fn frame(&self) -> String {
+        let text = self.text.trim_end_matches(';').replace(';', ",");
+        format!("{}:{} {}", self.line, self.column, text)
+    }
+}
+
+/// Executions and cumulative time of one statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementProfile {
+    pub line: usize,
+    pub column: usize,
+    /// The statement in canonical layout.
+    pub text: String,
+    pub count: u64,
+    pub total: Duration,
+}
+
+/// Calls and cumulative time of one function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    pub total: Duration,
+}
+
+#[derive(Default)]
+struct Totals {
+    count: u64,
+    total: Duration,
+}
+
+impl Totals {
+    fn add(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+    }
+}
+
+/// Debug hook that counts and times every statement and function call.
+///
+/// Install it through an `Rc<RefCell<_>>` to read the profile after the run:
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use whiteye::machine::Machine;
+/// use whiteye::parser::parse_with_spans;
+/// use whiteye::profile::Profiler;
+///
+/// let profiler = Rc::new(RefCell::new(Profiler::new()));
+/// let mut machine = Machine::new();
+/// machine.set_output(std::io::sink());
+/// machine.set_debug_hook(profiler.clone());
+/// for (statement, span) in parse_with_spans("println(1);\nprintln(2);\n").unwrap() {
+///     machine.run_at(statement, span).unwrap();
+/// }
+///
+/// let profiler = profiler.borrow();
+/// assert_eq!(profiler.statements().len(), 2);
+/// assert_eq!(profiler.functions()[0].name, "println");
+/// assert_eq!(profiler.functions()[0].calls, 2);
+/// ```
+#[derive(Default)]
+pub struct Profiler {
+    statements: BTreeMap<StatementKey, Totals>,
+    functions: HashMap<String, Totals>,
+    /// Time spent in each function, per statement that called it, for folded stacks.
+    calls: BTreeMap<(StatementKey, String), Duration>,
+    current: Option<(StatementKey, Instant)>,
+    running: Vec<(String, Instant)>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every statement run, slowest first.
+    pub fn statements(&self) -> Vec<StatementProfile> {
+        let mut statements: Vec<StatementProfile> = self
+            .statements
+            .iter()
+            .map(|(key, totals)| StatementProfile {
+                line: key.line,
+                column: key.column,
+                text: key.text.clone(),
+                count: totals.count,
+                total: totals.total,
+            })
+            .collect();
+        statements.sort_by_key(|s| std::cmp::Reverse(s.total));
+        statements
+    }
+
+    /// Every function called, slowest first.
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let mut functions: Vec<FunctionProfile> = self
+            .functions
+            .iter()
+            .map(|(name, totals)| FunctionProfile {
+                name: name.clone(),
+                calls: totals.count,
+                total: totals.total,
+            })
+            .collect();
+        functions.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+        functions
+    }
+
+    /// The hotspot report printed by `whiteye --profile`.
+    pub fn report(&self) -> String {
+        let mut report = String::from("statements by total time:\n");
+        let _ = writeln!(
+            report,
+            "{:>10} {:>12}  {:<9} statement",
+            "count", "total", "line"
+        );
+        for s in self.statements() {
+            let location = format!("{}:{}", s.line, s.column);
+            let total = format!("{:?}", s.total);
+            let _ = writeln!(
+                report,
+                "{:>10} {:>12}  {:<9} {}",
+                s.count, total, location, s.text
+            );
+        }
+        report.push_str("\nfunctions by total time:\n");
+        let _ = writeln!(report, "{:>10} {:>12}  function", "calls", "total");
+        for f in self.functions() {
+            let total = format!("{:?}", f.total);
+            let _ = writeln!(report, "{:>10} {:>12}  {}", f.calls, total, f.name);
+        }
+        report
+    }
+
+    /// The profile as folded stacks, one `main;statement[;function] nanoseconds` line
+    /// per frame, as read by flamegraph tools. Statement lines count only the time not
+    /// spent in functions.
+    pub fn folded(&self) -> String {
+        let mut folded = String::new();
+        for (key, totals) in &self.statements {
+            let in_functions: Duration = self
+                .calls
+                .iter()
+                .filter(|((statement, _), _)| statement == key)
+                .map(|(_, time)| *time)
+                .sum();
+            let own = totals.total.saturating_sub(in_functions);
+            let _ = writeln!(folded, "main;{} {}", key.frame(), own.as_nanos());
+        }
+        for ((key, name), time) in &self.calls {
+            let _ = writeln!(folded, "main;{};{} {}", key.frame(), name, time.as_nanos());
+        }
+        folded
+    }
+}
+
+impl DebugHook for Profiler {
+    fn before_statement(&mut self, machine: &mut Machine, statement: &Ast) -> Progress {
+        self.current = Some((StatementKey::new(machine, statement), Instant::now()));
+        Progress::Continue
+    }
+
+    fn after_statement(&mut self, _: &Machine, _: &Ast) {
+        if let Some((key, started)) = self.current.take() {
+            self.statements
+                .entry(key)
+                .or_default()
+                .add(started.elapsed());
+        }
+    }
+
+    fn enter_function(&mut self, _: &Machine, name: &str) {
+        self.running.push((name.to_string(), Instant::now()));
+    }
+
+    fn exit_function(&mut self, _: &Machine, _: &str) {
+        if let Some((name, started)) = self.running.pop() {
+            let elapsed = started.elapsed();
+            if let Some((key, _)) = &self.current {
+                *self.calls.entry((key.clone(), name.clone())).or_default() += elapsed;
+            }
+            self.functions.entry(name).or_default().add(elapsed);
+        }
+    }
+}
+
+/// Debug hook that writes each statement with its location, followed by the
+/// variables it declared or changed.
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use whiteye::machine::Machine;
+/// use whiteye::parser::parse_with_spans;
+/// use whiteye::profile::Tracer;
+///
+/// let tracer = Rc::new(RefCell::new(Tracer::new(Vec::new())));
+/// let mut machine = Machine::new();
+/// machine.set_debug_hook(tracer.clone());
+/// for (statement, span) in parse_with_spans("let x: int = 1;\nx *= 5;\n").unwrap() {
+///     machine.run_at(statement, span).unwrap();
+/// }
+///
+/// let trace = String::from_utf8(tracer.borrow().output().clone()).unwrap();
+/// assert_eq!(trace, "1:1 let x: int = 1;\n    x = 1\n2:1 x *= 5;\n    x = 5\n");
+/// ```
+pub struct Tracer<W> {
+    output: W,
+    before: HashMap<String, Value>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(output: W) -> Self {
+        Tracer {
+            output,
+            before: HashMap::new(),
+        }
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+}
+
+impl<W: Write> DebugHook for Tracer<W> {
+    fn before_statement(&mut self, machine: &mut Machine, statement: &Ast) -> Progress {
+        let text = format_statement(statement);
+        let _ = match machine.location() {
+            Some(span) => writeln!(self.output, "{}:{} {}", span.line, span.column, text),
+            None => writeln!(self.output, "{}", text),
+        };
+        self.before = machine.variables.clone();
+        Progress::Continue
+    }
+
+    fn after_statement(&mut self, machine: &Machine, _: &Ast) {
+        let changed: BTreeMap<&String, &Value> = machine
+            .variables
+            .iter()
+            .filter(|(name, value)| self.before.get(*name) != Some(value))
+            .collect();
+        for (name, value) in changed {
+            let _ = writeln!(self.output, "    {} = {}", name, value);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Bounds on the work a script may do, checked by `Machine` while it runs.
+///
+/// `None` leaves that resource unbounded. Steps and elapsed time are counted from
+/// the last `Machine::reset_usage`, so one budget can span several `run` calls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    /// Statements run plus expressions evaluated.
+    pub max_steps: Option<u64>,
+    /// How many function calls may be in progress at once.
+    pub max_call_depth: Option<usize>,
+    /// How deeply expressions may nest while being evaluated.
+    pub max_expression_depth: Option<usize>,
+    /// Largest string (in bytes) or list (in elements, counting what they contain)
+    /// a function may return or `+` may build. Built-ins check this before
+    /// allocating, so a script cannot exhaust memory by asking for a huge value.
+    pub max_value_size: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+impl Limits {
+    /// No limits at all, not even on expression depth.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_steps: None,
+            max_call_depth: None,
+            max_expression_depth: None,
+            max_value_size: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Default for Limits {
+    /// Unbounded except for expression depth, which is capped so that deeply
+    /// nested source fails with an error instead of overflowing the stack.
+    fn default() -> Self {
+        Limits {
+            max_expression_depth: Some(DEFAULT_MAX_EXPRESSION_DEPTH),
+            ..Limits::unlimited()
+        }
+    }
+}
+
+pub const DEFAULT_MAX_EXPRESSION_DEPTH: usize = 256;
+
+/// Something outside the machine that a built-in function may touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    FileSystem,
+    Environment,
+    Time,
+    /// The process's standard input and error streams.
+    Console,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::FileSystem => "file system",
+            Capability::Environment => "environment",
+            Capability::Time => "time",
+            Capability::Console => "console",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Which capabilities built-in functions are allowed to use. All are granted by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub file_system: bool,
+    pub environment: bool,
+    pub time: bool,
+    pub console: bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Capabilities {
+            file_system: true,
+            environment: true,
+            time: true,
+            console: true,
+        }
+    }
+
+    /// Denies everything; for running untrusted scripts.
+    pub fn none() -> Self {
+        Capabilities {
+            file_system: false,
+            environment: false,
+            time: false,
+            console: false,
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::FileSystem => self.file_system,
+            Capability::Environment => self.environment,
+            Capability::Time => self.time,
+            Capability::Console => self.console,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::all()
+    }
+}
+
+/// Lets another thread stop a running `Machine`, obtained from `Machine::interrupt_handle`.
+///
+/// The machine checks the flag on every step and function call and stops with
+/// `MachineError::Interrupted`. The flag stays set until `clear` is called.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// What a progress callback wants the machine to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    Continue,
+    Abort,
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::module::Module;
+use crate::value::Value;
+
+/// Written at the start of every snapshot so that other JSON is not mistaken for one.
+pub const SNAPSHOT_FORMAT: &str = "whiteye-snapshot";
+
+/// Bumped whenever the layout of `Snapshot` changes incompatibly.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("not a Whiteye snapshot")]
+    NotASnapshot,
+    #[error("snapshot version {found} is not supported (expected {supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("snapshot needs function {0}, which is not registered")]
+    MissingFunction(String),
+    #[error("malformed snapshot: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Saved state of a `Machine`, taken with `Machine::snapshot`.
+///
+/// Statements run to completion, so a machine between `run` calls has no call stack
+/// to save: a snapshot is its variables, the modules it has imported and under which
+/// names, and its usage counters. Modules are saved with the values they export, so
+/// restoring does not run them again. Host functions cannot be
+/// saved; only their names are recorded, and `Machine::restore` checks that the
+/// restoring machine has registered them all. Limits, capabilities and I/O are host
+/// configuration and are left alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    format: String,
+    version: u32,
+    pub variables: BTreeMap<String, Value>,
+    /// Each name an `import` bound, with the module bound to it.
+    pub imports: BTreeMap<String, Module>,
+    /// Every module file run so far, which later imports of it reuse.
+    pub modules: Vec<Module>,
+    pub functions: Vec<String>,
+    pub steps: u64,
+}
+
+/// The part of a snapshot that must stay readable across versions.
+#[derive(Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+}
+
+impl Snapshot {
+    pub(crate) fn new(
+        variables: BTreeMap<String, Value>,
+        imports: BTreeMap<String, Module>,
+        modules: Vec<Module>,
+        functions: Vec<String>,
+        steps: u64,
+    ) -> Self {
+        Snapshot {
+            format: SNAPSHOT_FORMAT.to_string(),
+            version: SNAPSHOT_VERSION,
+            variables,
+            imports,
+            modules,
+            functions,
+            steps,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("snapshots always serialize")
+    }
+
+    /// Reads a snapshot written by `to_json`, rejecting other formats and versions
+    /// before looking at the rest of the data.
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let header: Header = serde_json::from_str(json).map_err(|_| SnapshotError::NotASnapshot)?;
+        if header.format != SNAPSHOT_FORMAT {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        if header.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: header.version,
+                supported: SNAPSHOT_VERSION,
+            });
+        }
+        Ok(serde_json::from_str(json)?)
+    }
+}
@@ -0,0 +1,153 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::ast::Ast;
+use crate::lexer::{tokenize, Span, Token};
+use crate::machine::{Machine, MachineError};
+use crate::module::module_path_from_env;
+use crate::parser::{parse_tokens, statement_span, statement_tokens, test_body_tokens, ParseError};
+
+/// Outcome of one `test "name" { ... }` block.
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    /// Where the `test` keyword is.
+    pub span: Span,
+    pub failure: Option<Failure>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub error: MachineError,
+    /// The statement that failed.
+    pub location: Span,
+    /// Everything the test printed before it failed.
+    pub output: String,
+}
+
+impl Failure {
+    /// Formats the failure with the failing source line, for a file named `path`.
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let mut text = format!(
+            "{}:{}:{}: {}\n{}\n",
+            path,
+            self.location.line,
+            self.location.column,
+            self.error,
+            self.location.excerpt(source)
+        );
+        if !self.output.is_empty() {
+            text.push_str("output:\n");
+            text.push_str(&self.output);
+        }
+        text
+    }
+}
+
+/// Runs every test block in `source`, in order.
+///
+/// Each test gets a fresh `Machine`, which first runs the file's top-level statements
+/// and then the test body, so tests can check what the script sets up. What they
+/// print is captured rather than shown.
+///
+/// ```
+/// use whiteye::testing::run_tests;
+///
+/// let source = "let x: int = 2;\n\
+///               test \"doubles\" { assert_eq(x * 2, 4); }\n\
+///               test \"halves\" { assert(x / 2 == 2, x); }\n";
+/// let results = run_tests(source).unwrap();
+/// assert!(results[0].passed());
+/// let failure = results[1].failure.as_ref().unwrap();
+/// assert_eq!(failure.error.to_string(), "Assertion failed: 2");
+/// assert_eq!(failure.location.line, 3);
+/// ```
+pub fn run_tests(source: &str) -> Result<Vec<TestResult>, Vec<ParseError>> {
+    run(None, source)
+}
+
+/// Like `run_tests`, for the source of the file at `path`: its imports are resolved
+/// from there and from `WHITEYE_PATH`.
+pub fn run_tests_in(path: &Path, source: &str) -> Result<Vec<TestResult>, Vec<ParseError>> {
+    run(Some(path), source)
+}
+
+fn run(path: Option<&Path>, source: &str) -> Result<Vec<TestResult>, Vec<ParseError>> {
+    let (tokens, _) = tokenize(source)?;
+    let program = parse_tokens(source, &tokens)?;
+    let statements: Vec<(&Ast, &[Token])> = program.iter().zip(statement_tokens(&tokens)).collect();
+
+    let setup: Vec<(&Ast, &[Token])> = statements
+        .iter()
+        .filter(|(statement, _)| !matches!(statement, Ast::Test { .. }))
+        .cloned()
+        .collect();
+    let results = statements
+        .iter()
+        .filter_map(|(statement, tokens)| match statement {
+            Ast::Test { name, body } => {
+                let body = body.iter().zip(statement_tokens(test_body_tokens(tokens)));
+                Some(TestResult {
+                    name: name.clone(),
+                    span: tokens[0].span,
+                    failure: run_test(path, setup.iter().cloned().chain(body)),
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    Ok(results)
+}
+
+fn run_test<'a>(
+    path: Option<&Path>,
+    statements: impl Iterator<Item = (&'a Ast, &'a [Token])>,
+) -> Option<Failure> {
+    let output = Rc::new(RefCell::new(String::new()));
+    let sink = output.clone();
+    let mut machine = Machine::new();
+    if let Some(path) = path {
+        machine.set_script_path(path);
+        machine.set_module_path(module_path_from_env());
+    }
+    machine.set_output_fn(move |text| sink.borrow_mut().push_str(text));
+    for (statement, tokens) in statements {
+        let location = statement_span(tokens);
+        if let Err(error) = machine.run_at(statement.clone(), location) {
+            return Some(Failure {
+                error,
+                location,
+                output: output.take(),
+            });
+        }
+    }
+    None
+}
+
+/// The `.wy` files to test under `path`: the file itself, or every one in the
+/// directory and its subdirectories, sorted.
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(discover(&path)?);
+        } else if path.extension().is_some_and(|e| e == "wy") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
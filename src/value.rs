@@ -4,23 +4,220 @@ use std::{
     ops::{Add, Div, Mul, Neg, Sub},
 };
 
-#[derive(Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+use crate::machine::MachineError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
 pub enum Value {
     Integer(isize),
-    Float(f64),
+    Float(#[serde(with = "float_repr")] f64),
     Bool(bool),
+    String(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+        }
+    }
+}
+
+/// Numbers are equal when their values are, whatever their types, as `==` is in
+/// scripts: `1 == 1.0`. Values of other differing types are never equal.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Integer(l), Value::Integer(r)) => l == r,
+            (Value::Integer(l), Value::Float(r)) => *l as f64 == *r,
+            (Value::Float(l), Value::Integer(r)) => *l == *r as f64,
+            (Value::Float(l), Value::Float(r)) => l == r,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::List(l), Value::List(r)) => l == r,
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Value::Integer(v) => write!(f, "{}", *v),
-            Value::Float(v) => write!(f, "{}", *v),
+            Value::Float(v) => write!(f, "{}", format_float(*v)),
             Value::Bool(v) => write!(f, "{}", *v),
+            Value::String(v) => write!(f, "{}", v),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// The canonical display of a float: whole numbers keep a `.0` so that they read as
+/// floats, and non-finite values are written as their literals `inf`, `-inf` and `nan`.
+fn format_float(v: f64) -> String {
+    if v.is_nan() {
+        "nan".to_string()
+    } else if v.is_infinite() {
+        if v > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else if v.fract() == 0.0 {
+        format!("{:.1}", v)
+    } else {
+        v.to_string()
+    }
+}
+
+/// Serializes floats as numbers, except infinities and NaN, which JSON cannot
+/// represent and are written as the strings `inf`, `-inf` and `nan`.
+pub(crate) mod float_repr {
+    use serde::de::{self, Deserializer, Unexpected};
+    use serde::{Deserialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if v.is_finite() {
+            serializer.serialize_f64(*v)
+        } else if v.is_nan() {
+            serializer.serialize_str("nan")
+        } else if *v > 0.0 {
+            serializer.serialize_str("inf")
+        } else {
+            serializer.serialize_str("-inf")
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Number(f64),
+        Text(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(v) => Ok(v),
+            Repr::Text(s) => match s.as_str() {
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                "nan" => Ok(f64::NAN),
+                _ => Err(de::Error::invalid_value(
+                    Unexpected::Str(&s),
+                    &"a number, `inf`, `-inf` or `nan`",
+                )),
+            },
+        }
+    }
+}
+
+/// Conversion from a script value into a Rust type, used for host function arguments.
+pub trait FromValue: Sized {
+    /// Name of the accepted script type, for error messages.
+    fn type_name() -> String;
+
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+/// Conversion from a Rust type into a script value, used for host function results.
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl FromValue for Value {
+    fn type_name() -> String {
+        "any".to_string()
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromValue for isize {
+    fn type_name() -> String {
+        "int".to_string()
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Integer(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn type_name() -> String {
+        "float".to_string()
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Integer(v) => Some(v as f64),
+            Value::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn type_name() -> String {
+        "bool".to_string()
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    fn type_name() -> String {
+        "string".to_string()
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn type_name() -> String {
+        format!("list of {}", T::type_name())
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::List(values) => values.into_iter().map(T::from_value).collect(),
+            _ => None,
         }
     }
 }
 
+impl<T: Into<Value>> IntoValue for T {
+    fn into_value(self) -> Value {
+        self.into()
+    }
+}
+
 impl From<isize> for Value {
     fn from(v: isize) -> Self {
         Value::Integer(v)
@@ -39,120 +236,139 @@ impl From<bool> for Value {
     }
 }
 
-impl Add for Value {
-    type Output = Value;
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
 
-    fn add(self, rhs: Self) -> Self::Output {
-        match self {
-            Value::Integer(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Integer(v_lhs + v),
-                Value::Float(v) => Value::Float(v_lhs as f64 + v),
-                _ => panic!(),
-            },
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(v.to_string())
+    }
+}
 
-            Value::Float(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Float(v_lhs + v as f64),
-                Value::Float(v) => Value::Float(v_lhs + v),
-                _ => panic!(),
-            },
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::List(v.into_iter().map(Into::into).collect())
+    }
+}
+
+/// `left operator right` on ints, failing instead of wrapping or panicking.
+fn checked_integer(
+    left: isize,
+    operator: &str,
+    right: isize,
+    op: fn(isize, isize) -> Option<isize>,
+) -> Result<Value, MachineError> {
+    match op(left, right) {
+        Some(v) => Ok(Value::Integer(v)),
+        None if operator == "/" && right == 0 => Err(MachineError::DivisionByZero),
+        None => Err(MachineError::IntegerOverflow(format!(
+            "{} {} {}",
+            left, operator, right
+        ))),
+    }
+}
+
+/// `left operator right` on numbers: checked if both are ints, otherwise on floats.
+fn arithmetic(
+    left: Value,
+    operator: &'static str,
+    right: Value,
+    integer: fn(isize, isize) -> Option<isize>,
+    float: fn(f64, f64) -> f64,
+) -> Result<Value, MachineError> {
+    match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => checked_integer(l, operator, r, integer),
+        (Value::Integer(l), Value::Float(r)) => Ok(Value::Float(float(l as f64, r))),
+        (Value::Float(l), Value::Integer(r)) => Ok(Value::Float(float(l, r as f64))),
+        (Value::Float(l), Value::Float(r)) => Ok(Value::Float(float(l, r))),
+        (l, r) => Err(MachineError::TypeMismatch {
+            operator,
+            left: l.type_name(),
+            right: r.type_name(),
+        }),
+    }
+}
+
+impl Add for Value {
+    type Output = Result<Value, MachineError>;
 
-            _ => panic!(),
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
+            (l, r) => arithmetic(l, "+", r, isize::checked_add, |l, r| l + r),
         }
     }
 }
 
 impl Sub for Value {
-    type Output = Value;
+    type Output = Result<Value, MachineError>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        match self {
-            Value::Integer(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Integer(v_lhs - v),
-                Value::Float(v) => Value::Float(v_lhs as f64 - v),
-                _ => panic!(),
-            },
-
-            Value::Float(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Float(v_lhs + v as f64),
-                Value::Float(v) => Value::Float(v_lhs + v),
-                _ => panic!(),
-            },
-
-            _ => panic!(),
-        }
+        arithmetic(self, "-", rhs, isize::checked_sub, |l, r| l - r)
     }
 }
 
 impl Mul for Value {
-    type Output = Value;
+    type Output = Result<Value, MachineError>;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        match self {
-            Value::Integer(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Integer(v_lhs * v),
-                Value::Float(v) => Value::Float(v_lhs as f64 * v),
-                _ => panic!(),
-            },
-
-            Value::Float(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Float(v_lhs * v as f64),
-                Value::Float(v) => Value::Float(v_lhs * v),
-                _ => panic!(),
-            },
-
-            _ => panic!(),
-        }
+        arithmetic(self, "*", rhs, isize::checked_mul, |l, r| l * r)
     }
 }
 
 impl Div for Value {
-    type Output = Value;
+    type Output = Result<Value, MachineError>;
 
     fn div(self, rhs: Self) -> Self::Output {
-        match self {
-            Value::Integer(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Integer(v_lhs / v),
-                Value::Float(v) => Value::Float(v_lhs as f64 / v),
-                _ => panic!(),
-            },
-
-            Value::Float(v_lhs) => match rhs {
-                Value::Integer(v) => Value::Float(v_lhs / v as f64),
-                Value::Float(v) => Value::Float(v_lhs / v),
-                _ => panic!(),
-            },
-
-            _ => panic!(),
-        }
+        arithmetic(self, "/", rhs, isize::checked_div, |l, r| l / r)
     }
 }
 
 impl Neg for Value {
-    type Output = Value;
+    type Output = Result<Value, MachineError>;
 
     fn neg(self) -> Self::Output {
         match self {
-            Value::Integer(v) => Value::Integer(-v),
-            Value::Float(v) => Value::Float(-v),
-            _ => panic!(),
+            Value::Integer(v) => v
+                .checked_neg()
+                .map(Value::Integer)
+                .ok_or_else(|| MachineError::IntegerOverflow(format!("-({})", v))),
+            Value::Float(v) => Ok(Value::Float(-v)),
+            other => Err(MachineError::UnaryTypeMismatch {
+                operator: "-",
+                operand: other.type_name(),
+            }),
         }
     }
 }
 
+impl Value {
+    /// Whether `self` and `other` can be ordered with `<` and the like: both
+    /// numbers or both strings.
+    pub fn is_comparable_with(&self, other: &Value) -> bool {
+        matches!(
+            (self, other),
+            (
+                Value::Integer(_) | Value::Float(_),
+                Value::Integer(_) | Value::Float(_)
+            ) | (Value::String(_), Value::String(_))
+        )
+    }
+}
+
+/// Numbers are ordered among themselves, as are strings; any other pair is unordered.
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match self {
-            Value::Integer(v_lhs) => match other {
-                Value::Integer(v) => v_lhs.partial_cmp(v),
-                Value::Float(v) => (*v_lhs as f64).partial_cmp(v),
-                _ => panic!(),
-            },
-            Value::Float(v_lhs) => match other {
-                Value::Integer(v) => v_lhs.partial_cmp(&(*v as f64)),
-                Value::Float(v) => v_lhs.partial_cmp(v),
-                _ => panic!(),
-            },
-            _ => panic!(),
+        match (self, other) {
+            (Value::Integer(l), Value::Integer(r)) => l.partial_cmp(r),
+            (Value::Integer(l), Value::Float(r)) => (*l as f64).partial_cmp(r),
+            (Value::Float(l), Value::Integer(r)) => l.partial_cmp(&(*r as f64)),
+            (Value::Float(l), Value::Float(r)) => l.partial_cmp(r),
+            (Value::String(l), Value::String(r)) => l.partial_cmp(r),
+            _ => None,
         }
     }
 }
@@ -163,7 +379,7 @@ impl TryInto<bool> for Value {
     fn try_into(self) -> Result<bool, Self::Error> {
         match self {
             Value::Bool(v) => Ok(v),
-            _ => panic!(),
+            _ => Err("not a bool"),
         }
     }
 }
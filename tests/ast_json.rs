@@ -0,0 +1,67 @@
+use whiteye::ast::{self, AssignmentOpKind, Ast, ExprOpKind};
+use whiteye::machine::Machine;
+use whiteye::parser::parse;
+use whiteye::Value;
+
+#[test]
+fn parsed_programs_round_trip() {
+    let program = parse(
+        "let x: float = -1.5 * (2 + 3);\nlet ok: bool = x >= 0 || x < 1;\nx /= 2;\nprintln(x);",
+    )
+    .unwrap();
+    assert_eq!(ast::from_json(&ast::to_json(&program)).unwrap(), program);
+}
+
+#[test]
+fn synthesized_json_runs_on_the_machine() {
+    let json = r#"[
+        {"VariableDeclaration": {
+            "name": "n",
+            "value_type": "Integer",
+            "expr": {"Literal": {"type": "integer", "value": 20}}
+        }},
+        {"VariableAssignment": {
+            "name": "n",
+            "operator": "AMul",
+            "expr": {"Expr": {
+                "left": {"Literal": {"type": "integer", "value": 1}},
+                "operator": "EAdd",
+                "right": {"Literal": {"type": "float", "value": "inf"}}
+            }}
+        }}
+    ]"#;
+    let program = ast::from_json(json).unwrap();
+    assert!(matches!(
+        &program[1],
+        Ast::VariableAssignment {
+            operator: AssignmentOpKind::AMul,
+            expr,
+            ..
+        } if matches!(**expr, Ast::Expr { operator: ExprOpKind::EAdd, .. })
+    ));
+
+    let mut machine = Machine::new();
+    for statement in program {
+        machine.run(statement).unwrap();
+    }
+    assert_eq!(
+        machine.variables.get("n"),
+        Some(&Value::Float(f64::INFINITY))
+    );
+}
+
+#[test]
+fn misplaced_nodes_are_errors() {
+    let mut machine = Machine::new();
+    let mut program = ast::from_json(r#"[{"Literal": {"type": "integer", "value": 1}}]"#).unwrap();
+    let e = machine.run(program.remove(0)).unwrap_err();
+    assert_eq!(e.to_string(), "Expected a statement, found literal");
+
+    let json = r#"[{"FunctionCall": {
+        "name": "print",
+        "arguments": [{"Import": {"module": {"Name": "math"}, "alias": null}}]
+    }}]"#;
+    let mut program = ast::from_json(json).unwrap();
+    let e = machine.run(program.remove(0)).unwrap_err();
+    assert_eq!(e.to_string(), "Expected an expression, found import");
+}
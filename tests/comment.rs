@@ -0,0 +1,60 @@
+use whiteye::lexer::{tokenize, CommentKind, TokenKind};
+use whiteye::machine::Machine;
+use whiteye::parser::parse;
+use whiteye::value::Value;
+
+fn run(source: &str) -> Machine {
+    let mut machine = Machine::new();
+    for ast in parse(source).unwrap() {
+        machine.run(ast).unwrap();
+    }
+    machine
+}
+
+#[test]
+fn line_comments_end_at_the_line() {
+    let machine = run("let x: int = 1; // x = 2;\n// x = 3;\r\nx += 1;");
+    assert_eq!(machine.variables.get("x"), Some(&Value::Integer(2)));
+}
+
+#[test]
+fn block_comments_nest() {
+    let machine = run("/* outer /* inner */ let x: int = 1; */\nlet x: int = 2;");
+    assert_eq!(machine.variables.get("x"), Some(&Value::Integer(2)));
+}
+
+#[test]
+fn comments_may_sit_inside_expressions() {
+    let machine = run("let x: int = 1 + /* two */ 2 // three\n * 3;");
+    assert_eq!(machine.variables.get("x"), Some(&Value::Integer(7)));
+}
+
+#[test]
+fn unterminated_block_comment_points_at_its_start() {
+    let errors = parse("let x: int = 1;\n  /* open /* closed */\nx = 2;").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].span.line, errors[0].span.column), (2, 3));
+    assert_eq!(errors[0].message, "unterminated block comment");
+}
+
+#[test]
+fn comments_are_kept_apart_from_tokens() {
+    let source = "// head\nlet x: int = /* a /* b */ */ 1;";
+    let (tokens, comments) = tokenize(source).unwrap();
+    assert!(tokens.iter().all(|t| t.kind != TokenKind::Slash));
+    let table: Vec<(CommentKind, &str, usize, usize)> = comments
+        .iter()
+        .map(|c| (c.kind, c.text.as_str(), c.span.line, c.span.column))
+        .collect();
+    assert_eq!(
+        table,
+        vec![
+            (CommentKind::Line, "// head", 1, 1),
+            (CommentKind::Block, "/* a /* b */ */", 2, 14),
+        ]
+    );
+    assert_eq!(
+        &source[comments[1].span.start..comments[1].span.end],
+        comments[1].text
+    );
+}
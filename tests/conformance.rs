@@ -0,0 +1,103 @@
+//! Golden-file conformance suite.
+//!
+//! Every `tests/cases/*.wy` file is parsed and run, and what it prints and the error
+//! it stops with are compared against the `.out` and `.err` files next to it. A
+//! missing golden file means no output is expected. Run with `WHITEYE_BLESS=1` to
+//! write the current results as the new golden files.
+
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use whiteye::machine::Machine;
+use whiteye::parser::parse_with_spans;
+
+const BLESS: &str = "WHITEYE_BLESS";
+
+/// Runs `source` and returns what it printed and its error output.
+fn run(source: &str) -> (String, String) {
+    let program = match parse_with_spans(source) {
+        Ok(program) => program,
+        Err(errors) => {
+            let rendered = errors.iter().map(|e| e.render(source) + "\n").collect();
+            return (String::new(), rendered);
+        }
+    };
+
+    let printed = Rc::new(RefCell::new(String::new()));
+    let sink = printed.clone();
+    let mut machine = Machine::new();
+    machine.set_output_fn(move |text| sink.borrow_mut().push_str(text));
+    let mut error = String::new();
+    for (statement, span) in program {
+        if let Err(e) = machine.run_at(statement, span) {
+            error = format!("{}:{}: {}\n", span.line, span.column, e);
+            break;
+        }
+    }
+    let printed = printed.take();
+    (printed, error)
+}
+
+fn cases() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
+    let mut cases: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "wy"))
+        .collect();
+    cases.sort();
+    cases
+}
+
+/// Compares `actual` with the golden file at `path`, or rewrites it when blessing.
+/// Empty output is stored as no file at all.
+fn check(path: &Path, actual: &str, bless: bool) -> Option<String> {
+    let expected = fs::read_to_string(path).unwrap_or_default();
+    if expected == actual {
+        return None;
+    }
+    if bless {
+        if actual.is_empty() {
+            fs::remove_file(path).unwrap();
+        } else {
+            fs::write(path, actual).unwrap();
+        }
+        return None;
+    }
+    Some(format!(
+        "{}\n--- expected\n{}--- actual\n{}",
+        path.display(),
+        expected,
+        actual
+    ))
+}
+
+#[test]
+fn golden_cases() {
+    let bless = env::var_os(BLESS).is_some();
+    let mut failures = Vec::new();
+    for case in cases() {
+        let source = fs::read_to_string(&case).unwrap();
+        // A panic is an interpreter bug, never an expected result, so it cannot be blessed.
+        let (out, err) = match panic::catch_unwind(AssertUnwindSafe(|| run(&source))) {
+            Ok(result) => result,
+            Err(_) => {
+                failures.push(format!("{}\npanicked", case.display()));
+                continue;
+            }
+        };
+        failures.extend(check(&case.with_extension("out"), &out, bless));
+        failures.extend(check(&case.with_extension("err"), &err, bless));
+    }
+    assert!(
+        failures.is_empty(),
+        "{} golden file(s) differ; rerun with {}=1 to accept the new output\n\n{}",
+        failures.len(),
+        BLESS,
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+use whiteye::debugger::{dap, Debugger};
+use whiteye::lsp;
+use whiteye::machine::{Machine, MachineError};
+use whiteye::parser::parse_with_spans;
+
+const PROGRAM: &str = "let x: int = 1;\nlet y: int = x + 2;\nprintln(y);\nx += y;\n";
+
+/// A writer whose contents can still be read after it is handed to the machine.
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Shared {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `PROGRAM` under the console debugger fed with `commands`, returning the
+/// result and everything written to the console.
+fn console(commands: &str, breakpoints: &[usize]) -> (Result<(), MachineError>, String) {
+    let console = Shared::default();
+    let mut debugger = Debugger::new(PROGRAM, Cursor::new(commands.to_string()), console.clone());
+    for line in breakpoints {
+        debugger.add_breakpoint(*line);
+    }
+    let mut machine = Machine::new();
+    machine.set_output(console.clone());
+    machine.set_debug_hook(debugger);
+    let result = parse_with_spans(PROGRAM)
+        .unwrap()
+        .into_iter()
+        .try_for_each(|(statement, span)| machine.run_at(statement, span));
+    (result, console.text())
+}
+
+#[test]
+fn console_steps_prints_and_continues_to_breakpoints() {
+    let (result, output) = console("next\np x + 1\nvars\nc\np y\nc\n", &[3]);
+    assert!(result.is_ok());
+    assert_eq!(
+        output,
+        "step at line 1: let x: int = 1;\n\
+         (wdb) step at line 2: let y: int = x + 2;\n\
+         (wdb) 2\n\
+         (wdb) x = 1\n\
+         (wdb) breakpoint at line 3: println(y);\n\
+         (wdb) 3\n\
+         (wdb) 3\n"
+    );
+}
+
+#[test]
+fn console_quit_and_end_of_input_abort_the_program() {
+    let (result, output) = console("b 4\nd 4\nd 4\nq\n", &[]);
+    assert!(matches!(result, Err(MachineError::Aborted)));
+    assert_eq!(
+        output,
+        "step at line 1: let x: int = 1;\n\
+         (wdb) breakpoint at line 4\n\
+         (wdb) deleted breakpoint at line 4\n\
+         (wdb) no breakpoint at line 4\n\
+         (wdb) "
+    );
+
+    let (result, _) = console("", &[]);
+    assert!(matches!(result, Err(MachineError::Aborted)));
+}
+
+fn request(seq: u64, command: &str, arguments: Value) -> Value {
+    json!({"seq": seq, "type": "request", "command": command, "arguments": arguments})
+}
+
+#[test]
+fn dap_session_stops_at_breakpoints_and_inspects_variables() {
+    let path = std::env::temp_dir().join(format!("whiteye-dap-{}.wy", std::process::id()));
+    std::fs::write(&path, PROGRAM).unwrap();
+
+    let mut input = Vec::new();
+    for message in &[
+        request(1, "initialize", json!({"adapterID": "whiteye"})),
+        request(2, "launch", json!({"program": path})),
+        request(3, "setBreakpoints", json!({"breakpoints": [{"line": 2}]})),
+        request(4, "configurationDone", json!({})),
+        request(5, "stackTrace", json!({"threadId": 1})),
+        request(6, "variables", json!({"variablesReference": 1})),
+        request(7, "evaluate", json!({"expression": "x * 10"})),
+        request(8, "evaluate", json!({"expression": "nope"})),
+        request(9, "next", json!({"threadId": 1})),
+        request(10, "continue", json!({"threadId": 1})),
+        request(11, "disconnect", json!({})),
+    ] {
+        lsp::write_message(&mut input, message).unwrap();
+    }
+    let output = Shared::default();
+    dap::run(Cursor::new(input), output.clone()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut output = Cursor::new(output.text().into_bytes());
+    let mut messages: Vec<Value> = Vec::new();
+    while let Some(body) = lsp::read_message(&mut output).unwrap() {
+        messages.push(serde_json::from_str(&body).unwrap());
+    }
+    let response = |seq: u64| {
+        messages
+            .iter()
+            .find(|m| m["type"] == "response" && m["request_seq"] == seq)
+            .unwrap()
+    };
+    let events: Vec<&Value> = messages
+        .iter()
+        .filter(|m| m["type"] == "event")
+        .map(|m| &m["event"])
+        .collect();
+
+    assert_eq!(
+        events,
+        [
+            "initialized",
+            "stopped",
+            "stopped",
+            "output",
+            "exited",
+            "terminated"
+        ]
+    );
+    let stops: Vec<&Value> = messages
+        .iter()
+        .filter(|m| m["event"] == "stopped")
+        .map(|m| &m["body"]["reason"])
+        .collect();
+    assert_eq!(stops, ["breakpoint", "step"]);
+
+    assert_eq!(
+        response(3)["body"]["breakpoints"],
+        json!([{"verified": true, "line": 2}])
+    );
+    assert_eq!(response(5)["body"]["stackFrames"][0]["line"], 2);
+    assert_eq!(
+        response(6)["body"]["variables"],
+        json!([{"name": "x", "value": "1", "type": "int", "variablesReference": 0}])
+    );
+    assert_eq!(response(7)["body"]["result"], "10");
+    assert_eq!(response(8)["success"], false);
+    assert_eq!(
+        messages.iter().find(|m| m["event"] == "output").unwrap()["body"]["output"],
+        "3\n"
+    );
+}
@@ -0,0 +1,85 @@
+use std::fs;
+
+use whiteye::machine::{Machine, MachineError};
+use whiteye::parser::parse;
+use whiteye::sandbox::Limits;
+use whiteye::Value;
+
+fn run(machine: &mut Machine, source: &str) -> Result<(), MachineError> {
+    for ast in parse(source).unwrap() {
+        machine.run(ast)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn files_are_written_appended_and_read() {
+    let dir = std::env::temp_dir().join(format!("whiteye-files-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.txt");
+    let _ = fs::remove_file(&path);
+    let path = path.to_str().unwrap().to_string();
+
+    let mut machine = Machine::new();
+    machine
+        .variables
+        .insert("path".to_string(), Value::from(path.as_str()));
+    run(
+        &mut machine,
+        "let before: bool = exists(path);\n\
+         write_file(path, \"一\\n\");\n\
+         append_file(path, \"二\\n\");\n\
+         let text: string = read_file(path);\n\
+         let lines: list = read_lines(path);\n\
+         let after: bool = exists(path);",
+    )
+    .unwrap();
+    assert_eq!(machine.variables["before"], Value::Bool(false));
+    assert_eq!(machine.variables["after"], Value::Bool(true));
+    assert_eq!(machine.variables["text"], Value::from("一\n二\n"));
+    assert_eq!(machine.variables["lines"], Value::from(vec!["一", "二"]));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "一\n二\n");
+}
+
+#[test]
+fn io_failures_name_the_path() {
+    let mut machine = Machine::new();
+    let error = run(
+        &mut machine,
+        "println(read_file(\"/nonexistent/whiteye\"));",
+    )
+    .unwrap_err();
+    match error {
+        MachineError::FunctionFailed { name, message } => {
+            assert_eq!(name, "read_file");
+            assert!(message.starts_with("/nonexistent/whiteye: "), "{}", message);
+        }
+        error => panic!("unexpected error {:?}", error),
+    }
+}
+
+#[test]
+fn files_over_the_size_limit_are_not_read() {
+    let dir = std::env::temp_dir().join(format!("whiteye-large-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("large.txt");
+    fs::write(&path, "x\n".repeat(600)).unwrap();
+
+    let mut machine = Machine::new();
+    machine.set_limits(Limits {
+        max_value_size: Some(1000),
+        ..Limits::default()
+    });
+    machine
+        .variables
+        .insert("path".to_string(), Value::from(path.to_str().unwrap()));
+    for function in ["read_file", "read_lines"] {
+        let source = format!("let x: list = {}(path);", function);
+        match run(&mut machine, &source).unwrap_err() {
+            MachineError::SizeLimitExceeded { name, size, limit } => {
+                assert_eq!((name.as_str(), size, limit), (function, 1200, 1000))
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+}
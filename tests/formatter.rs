@@ -0,0 +1,60 @@
+use whiteye::formatter::{format_program, format_source};
+use whiteye::parser::parse;
+
+const PROGRAMS: &[&str] = &[
+    "let x: int = 1 + 2 * 3 - 4 / 2;",
+    "let x: int = (1 + 2) * (3 - 4) / -(2 * 5);",
+    "let x: int = 1 - (2 - 3) - 4;\nlet y: int = 8 / (4 / 2) * 2;",
+    "let a: float = --1.5 + +2.0e10 - 0.1;\nlet b: float = 1e-7 * inf;",
+    "let ok: bool = true;\nok = 1 + 1 == 2 && 3 > 2 || 4 <= 4;\nok = 1 != 2;",
+    "let n: int = 0x1f + 0b11;\nn += 1;\nn -= 2;\nn *= (3 + 4);\nn /= 5;",
+    "println(f(1, g(2 * (3 + 4)), h()));\nprintln(x >= 0 || x < 1);",
+    "let 名前: int = 1;\nprintln(名前);",
+    "let s: string = \"tab\\t \\\"quote\\\"\" + \"\";\nlet parts: list = split(s, \",\");",
+];
+
+#[test]
+fn printing_preserves_the_ast() {
+    for source in PROGRAMS {
+        let program = parse(source).unwrap();
+        let printed = format_program(&program);
+        assert_eq!(parse(&printed).unwrap(), program, "{}", printed);
+        assert_eq!(format_source(source).unwrap(), printed);
+    }
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for source in PROGRAMS {
+        let once = format_source(source).unwrap();
+        assert_eq!(format_source(&once).unwrap(), once);
+    }
+}
+
+#[test]
+fn comments_and_blank_lines() {
+    let source = "\
+// header
+
+
+let x:int=1;  // one
+/* between */ x+=(2);
+println(x /* inside */ );
+
+// footer
+";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "\
+// header
+
+let x: int = 1; // one
+/* between */
+x += 2;
+/* inside */
+println(x);
+
+// footer
+"
+    );
+}
@@ -0,0 +1,89 @@
+use whiteye::machine::{Machine, MachineError};
+use whiteye::parser::parse;
+use whiteye::value::Value;
+
+fn run(machine: &mut Machine, source: &str) -> Result<(), String> {
+    for ast in parse(source).unwrap() {
+        machine.run(ast).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn closures_of_different_arities() {
+    let mut machine = Machine::new();
+    machine.register_fn("answer", || 42isize);
+    machine.register_fn("half", |x: f64| x / 2.0);
+    machine.register_fn("add", |a: isize, b: isize| a + b);
+    machine.register_fn("between", |x: isize, lo: isize, hi: isize| {
+        lo <= x && x <= hi
+    });
+
+    run(
+        &mut machine,
+        "let a: int = add(answer(), 1) * 2;\nlet h: float = half(3);\nlet b: bool = between(a, 80, 90);",
+    )
+    .unwrap();
+    assert_eq!(machine.variables.get("a"), Some(&Value::Integer(86)));
+    assert_eq!(machine.variables.get("h"), Some(&Value::Float(1.5)));
+    assert_eq!(machine.variables.get("b"), Some(&Value::Bool(true)));
+}
+
+#[test]
+fn strings_and_lists_round_trip() {
+    let mut machine = Machine::new();
+    machine.register_fn("range", |n: isize| (0..n).collect::<Vec<isize>>());
+    machine.register_fn("sum", |values: Vec<isize>| values.iter().sum::<isize>());
+    machine.register_fn("greeting", || "こんにちは".to_string());
+    machine.register_fn("length", |s: String| s.chars().count() as isize);
+
+    run(
+        &mut machine,
+        "let s: int = sum(range(5));\nlet n: int = length(greeting());",
+    )
+    .unwrap();
+    assert_eq!(machine.variables.get("s"), Some(&Value::Integer(10)));
+    assert_eq!(machine.variables.get("n"), Some(&Value::Integer(5)));
+}
+
+#[test]
+fn arity_and_type_are_checked_at_call_time() {
+    let mut machine = Machine::new();
+    machine.register_fn("add", |a: isize, b: isize| a + b);
+
+    let ast = parse("add(1);").unwrap().remove(0);
+    let e = machine.run(ast).unwrap_err();
+    assert!(matches!(
+        e,
+        MachineError::ArgumentCount {
+            expected: 2,
+            found: 1,
+            ..
+        }
+    ));
+
+    let ast = parse("add(1, 2.5);").unwrap().remove(0);
+    let e = machine.run(ast).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Function add argument 2: expected int, found float"
+    );
+}
+
+#[test]
+fn errors_from_host_functions_are_propagated() {
+    let mut machine = Machine::new();
+    machine.register_fn("checked_div", |a: isize, b: isize| {
+        a.checked_div(b).ok_or("division by zero")
+    });
+    machine.register_fn("log", |_: isize| {});
+
+    assert_eq!(
+        run(&mut machine, "let x: int = checked_div(1, 0);"),
+        Err("Function checked_div failed: division by zero".to_string())
+    );
+    assert_eq!(
+        run(&mut machine, "let x: int = log(1);"),
+        Err("Function log does not return a value".to_string())
+    );
+}
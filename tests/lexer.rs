@@ -0,0 +1,100 @@
+use whiteye::lexer::{tokenize, Span, TokenKind};
+use whiteye::parser::parse;
+
+fn kinds(source: &str) -> Vec<TokenKind> {
+    let (tokens, _) = tokenize(source).unwrap();
+    tokens.into_iter().map(|t| t.kind).collect()
+}
+
+#[test]
+fn operators_are_matched_longest_first() {
+    assert_eq!(
+        kinds("a<=b==c&&d"),
+        vec![
+            TokenKind::Identifier("a".to_string()),
+            TokenKind::LessEqual,
+            TokenKind::Identifier("b".to_string()),
+            TokenKind::Equal,
+            TokenKind::Identifier("c".to_string()),
+            TokenKind::And,
+            TokenKind::Identifier("d".to_string()),
+        ]
+    );
+    assert_eq!(
+        kinds(">= != || += -= *= /="),
+        vec![
+            TokenKind::GreaterEqual,
+            TokenKind::NotEqual,
+            TokenKind::Or,
+            TokenKind::PlusAssign,
+            TokenKind::MinusAssign,
+            TokenKind::StarAssign,
+            TokenKind::SlashAssign,
+        ]
+    );
+    assert_eq!(kinds("< ="), vec![TokenKind::Less, TokenKind::Assign]);
+}
+
+#[test]
+fn runs_of_operators_that_form_no_operator_are_rejected() {
+    assert_eq!(
+        kinds("=!<"),
+        vec![TokenKind::Assign, TokenKind::Not, TokenKind::Less]
+    );
+    let errors = parse("let b: bool = 1 =!< 2;").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), "1:16: expected `;` after statement");
+
+    let errors = parse("let b: bool = 1 & 2;").unwrap_err();
+    assert_eq!(errors[0].message, "unexpected character `&`");
+}
+
+#[test]
+fn keywords_are_not_identifiers() {
+    assert_eq!(kinds("while"), vec![TokenKind::While]);
+    assert_eq!(
+        kinds("whiles"),
+        vec![TokenKind::Identifier("whiles".to_string())]
+    );
+    for keyword in ["let", "if", "while", "true", "default"] {
+        let source = format!("let {}: int = 1;", keyword);
+        let errors = parse(&source).unwrap_err();
+        assert_eq!(errors[0].span.column, 5, "{}", source);
+        assert_eq!(errors[0].found, format!("keyword `{}`", keyword));
+    }
+}
+
+#[test]
+fn tokens_carry_their_spans() {
+    let (tokens, _) = tokenize("let x: int = 10;\n  x += 名前;").unwrap();
+    let spans: Vec<(usize, usize, usize, usize)> = tokens
+        .iter()
+        .map(|t| (t.span.start, t.span.end, t.span.line, t.span.column))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (0, 3, 1, 1),
+            (4, 5, 1, 5),
+            (5, 6, 1, 6),
+            (7, 10, 1, 8),
+            (11, 12, 1, 12),
+            (13, 15, 1, 14),
+            (15, 16, 1, 16),
+            (19, 20, 2, 3),
+            (21, 23, 2, 5),
+            (24, 30, 2, 8),
+            (30, 31, 2, 10),
+        ]
+    );
+    let span = Span {
+        start: 19,
+        end: 20,
+        line: 2,
+        column: 3,
+    };
+    assert_eq!(
+        span.excerpt("let x: int = 10;\n  x += 名前;"),
+        "  x += 名前;\n  ^"
+    );
+}
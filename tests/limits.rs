@@ -0,0 +1,257 @@
+use std::thread;
+use std::time::Duration;
+
+use whiteye::machine::{Machine, MachineError};
+use whiteye::parser::{parse, MAX_NESTING};
+use whiteye::sandbox::{Capabilities, Capability, Limits, Progress};
+
+fn run(machine: &mut Machine, source: &str) -> Result<(), MachineError> {
+    for ast in parse(source).unwrap() {
+        machine.run(ast)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn step_limit_spans_runs_until_reset() {
+    let mut machine = Machine::new();
+    machine.set_limits(Limits {
+        max_steps: Some(7),
+        ..Limits::default()
+    });
+
+    run(&mut machine, "let x: int = 1 + 2;").unwrap();
+    assert_eq!(machine.steps(), 4);
+    assert!(matches!(
+        run(&mut machine, "x += 1 + 2;"),
+        Err(MachineError::StepLimitExceeded(7))
+    ));
+
+    machine.reset_usage();
+    run(&mut machine, "x += 1 + 2;").unwrap();
+}
+
+#[test]
+fn deep_expressions_fail_instead_of_overflowing() {
+    let source = format!("let x: int = {}1;", "1 + ".repeat(5000));
+    let mut machine = Machine::new();
+    assert!(matches!(
+        run(&mut machine, &source),
+        Err(MachineError::ExpressionDepthExceeded(_))
+    ));
+
+    // Source nested as deeply as the parser allows runs without overflowing.
+    machine.set_limits(Limits::unlimited());
+    let depth = MAX_NESTING;
+    let source = format!("let x: int = {}1{};", "(".repeat(depth), ")".repeat(depth));
+    run(&mut machine, &source).unwrap();
+    let source = format!("let x: int = {}1;", "-".repeat(depth));
+    run(&mut machine, &source).unwrap();
+    let source = format!(
+        "let x: int = {}1{};",
+        "abs(".repeat(depth),
+        ")".repeat(depth)
+    );
+    run(&mut machine, &source).unwrap();
+
+    for source in [
+        format!("let x: int = {}1{};", "(".repeat(2000), ")".repeat(2000)),
+        format!("let x: int = {}1;", "-".repeat(100_000)),
+        format!("let x: int = {}1{};", "(-".repeat(200), ")".repeat(200)),
+        format!("let x: int = {}1{};", "abs(".repeat(2000), ")".repeat(2000)),
+    ] {
+        let errors = parse(&source).unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            format!("expression nested more than {} levels deep", MAX_NESTING)
+        );
+    }
+}
+
+#[test]
+fn integer_arithmetic_fails_instead_of_panicking() {
+    let mut machine = Machine::new();
+    assert!(matches!(
+        run(&mut machine, "let x: int = 1 / 0;"),
+        Err(MachineError::DivisionByZero)
+    ));
+    for source in [
+        "let x: int = 9223372036854775807 + 1;",
+        "let x: int = -9223372036854775807 - 2;",
+        "let x: int = 3037000500 * 3037000500;",
+        "let x: int = (-9223372036854775807 - 1) / -1;",
+        "let x: int = -(-9223372036854775807 - 1);",
+        "let x: int = 9223372036854775807;\nx += 1;",
+    ] {
+        let e = run(&mut machine, source).unwrap_err();
+        assert!(
+            matches!(e, MachineError::IntegerOverflow(_)),
+            "{}: {:?}",
+            source,
+            e
+        );
+    }
+    let e = run(&mut machine, "let y: int = -(-9223372036854775807 - 1);").unwrap_err();
+    assert_eq!(e.to_string(), "Integer overflow in -(-9223372036854775808)");
+    run(&mut machine, "let z: float = 1 / 0.0;").unwrap();
+}
+
+#[test]
+fn call_depth_and_value_size() {
+    let mut machine = Machine::new();
+    machine.register_fn("repeat", |s: String, n: isize| s.repeat(n as usize));
+    machine.register_fn("length", |s: String| s.len() as isize);
+    machine.register_fn("text", || "ab".to_string());
+    machine.set_limits(Limits {
+        max_call_depth: Some(0),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        run(&mut machine, "let n: int = length(text());"),
+        Err(MachineError::CallDepthExceeded(0))
+    ));
+
+    machine.set_limits(Limits {
+        max_value_size: Some(5),
+        ..Limits::default()
+    });
+    run(&mut machine, "let n: int = length(repeat(text(), 2));").unwrap();
+    let e = run(&mut machine, "let n: int = length(repeat(text(), 3));").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Function repeat returned a value of size 6, over the limit of 5"
+    );
+}
+
+#[test]
+fn built_ins_check_the_size_limit_before_allocating() {
+    let mut machine = Machine::new();
+    machine.set_limits(Limits {
+        max_value_size: Some(1000),
+        ..Limits::default()
+    });
+    run(&mut machine, "let s: string = repeat(\"ab\", 500);").unwrap();
+    for (source, function) in [
+        ("println(repeat(\"x\", 1000000000000));", "repeat"),
+        ("println(format(\"{:100000000000000}\", 1));", "format"),
+        ("println(format(\"{:.60000}\", 1.5));", "format"),
+        ("println(format(\"{}{}\", s, s));", "format"),
+        ("println(replace(s, \"a\", s));", "replace"),
+        ("println(join(chars(\"abc\"), s));", "join"),
+    ] {
+        let e = run(&mut machine, source).unwrap_err();
+        assert!(
+            matches!(&e, MachineError::SizeLimitExceeded { name, limit: 1000, .. } if name == function),
+            "{}: {:?}",
+            source,
+            e
+        );
+    }
+    for source in ["println(s + s);", "let t: string = s;\nt += s;"] {
+        let e = run(&mut machine, source).unwrap_err();
+        assert!(
+            matches!(
+                e,
+                MachineError::ConcatenationTooLong {
+                    size: 2000,
+                    limit: 1000
+                }
+            ),
+            "{}: {:?}",
+            source,
+            e
+        );
+    }
+}
+
+#[test]
+fn timeout() {
+    let mut machine = Machine::new();
+    machine.register_fn("nap", || thread::sleep(Duration::from_millis(20)));
+    machine.set_limits(Limits {
+        timeout: Some(Duration::from_millis(10)),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        run(&mut machine, "nap();\nnap();"),
+        Err(MachineError::Timeout(_))
+    ));
+}
+
+#[test]
+fn capabilities_disable_functions() {
+    let mut machine = Machine::new();
+    machine.register_fn_with_capability("clock", Capability::Time, || 0isize);
+    run(&mut machine, "let t: int = clock();").unwrap();
+
+    machine.set_capabilities(Capabilities::none());
+    let e = run(&mut machine, "let t: int = clock();").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Function clock needs time access, which is disabled"
+    );
+}
+
+#[test]
+fn sandboxed_scripts_cannot_do_io() {
+    let mut machine = Machine::new();
+    machine.set_capabilities(Capabilities::none());
+    for (source, capability) in [
+        ("println(read_file(\"x\"));", Capability::FileSystem),
+        ("write_file(\"x\", \"\");", Capability::FileSystem),
+        ("println(exists(\"x\"));", Capability::FileSystem),
+        ("println(input());", Capability::Console),
+        ("eprint(1);", Capability::Console),
+        ("println(args());", Capability::Environment),
+    ] {
+        let e = run(&mut machine, source).unwrap_err();
+        assert!(
+            matches!(e, MachineError::CapabilityDisabled { capability: c, .. } if c == capability),
+            "{}: {:?}",
+            source,
+            e
+        );
+    }
+}
+
+#[test]
+fn interrupt_from_another_thread() {
+    let mut machine = Machine::new();
+    machine.register_fn("nap", || thread::sleep(Duration::from_millis(50)));
+    let handle = machine.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        handle.interrupt();
+    });
+
+    assert!(matches!(
+        run(&mut machine, "nap();\nnap();\nnap();"),
+        Err(MachineError::Interrupted)
+    ));
+    interrupter.join().unwrap();
+
+    machine.interrupt_handle().clear();
+    run(&mut machine, "let x: int = 1;").unwrap();
+}
+
+#[test]
+fn progress_callback_sees_the_machine() {
+    let mut machine = Machine::new();
+    machine.set_progress_callback(3, |machine| {
+        if machine.variables.contains_key("stop") {
+            Progress::Abort
+        } else {
+            Progress::Continue
+        }
+    });
+
+    run(&mut machine, "let x: int = 1 + 2 + 3;").unwrap();
+    assert!(matches!(
+        run(&mut machine, "let stop: bool = true;\nx += 1 + 2;"),
+        Err(MachineError::Aborted)
+    ));
+    assert_eq!(
+        machine.variables.get("x"),
+        Some(&whiteye::Value::Integer(6))
+    );
+}
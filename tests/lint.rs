@@ -0,0 +1,64 @@
+use whiteye::lint::{lint_source, LintConfig, Rule};
+
+fn lint(source: &str, config: &LintConfig) -> Vec<(Rule, usize, usize)> {
+    lint_source(source, config)
+        .unwrap()
+        .into_iter()
+        .map(|w| (w.rule, w.span.line, w.span.column))
+        .collect()
+}
+
+#[test]
+fn each_rule_reports_at_the_offending_token() {
+    let source = "\
+let x: float = 1.5;
+let unused: int = 2;
+y = 3;
+println(z + x);
+let z: int = 1;
+x = x;
+println(1 < 2 && f(x != 0.5) > 0);
+";
+    assert_eq!(
+        lint(source, &LintConfig::default()),
+        vec![
+            (Rule::UnusedVariable, 2, 5),
+            (Rule::UndeclaredAssignment, 3, 1),
+            (Rule::UseBeforeDeclare, 4, 9),
+            (Rule::UnusedVariable, 5, 5),
+            (Rule::SelfAssignment, 6, 1),
+            (Rule::ConstantCondition, 7, 11),
+            (Rule::FloatEquality, 7, 22),
+        ]
+    );
+}
+
+#[test]
+fn clean_programs_have_no_warnings() {
+    let source = "\
+let total: int = 0;
+let rate: float = 0.5;
+total += 2;
+println(total * rate >= 1 || total == 0);
+";
+    assert_eq!(lint(source, &LintConfig::default()), vec![]);
+}
+
+#[test]
+fn rules_can_be_disabled_by_config_and_comments() {
+    let source = "\
+// lint: allow(unused-variable)
+let a: int = base;
+let b: float = 0.5; /* lint: allow(unused-variable, float-equality) */
+println(b == 0.5);
+println(base);
+";
+    let config = LintConfig::from_toml("globals = [\"base\"]\n").unwrap();
+    assert_eq!(lint(source, &config), vec![(Rule::FloatEquality, 4, 11)]);
+
+    let config =
+        LintConfig::from_toml("globals = [\"base\"]\n\n[rules]\nfloat-equality = false\n").unwrap();
+    assert_eq!(lint(source, &config), vec![]);
+
+    assert!(LintConfig::from_toml("[rules]\nno-such-rule = true\n").is_err());
+}
@@ -0,0 +1,192 @@
+use std::io::Cursor;
+
+use serde_json::{json, Value};
+use whiteye::builtin_functions::CONSTANTS;
+use whiteye::lsp;
+use whiteye::machine::Machine;
+
+const URI: &str = "file:///main.wy";
+
+/// Frames `messages`, runs the server over them and returns everything it sent.
+fn exchange(messages: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        lsp::write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    lsp::run(Cursor::new(input), &mut output).unwrap();
+
+    let mut output = Cursor::new(output);
+    let mut replies = Vec::new();
+    while let Some(body) = lsp::read_message(&mut output).unwrap() {
+        replies.push(serde_json::from_str(&body).unwrap());
+    }
+    replies
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+fn open(text: &str) -> Value {
+    notification(
+        "textDocument/didOpen",
+        json!({"textDocument": {"uri": URI, "languageId": "whiteye", "version": 1, "text": text}}),
+    )
+}
+
+fn at(id: u64, method: &str, line: u64, character: u64) -> Value {
+    request(
+        id,
+        method,
+        json!({
+            "textDocument": {"uri": URI},
+            "position": {"line": line, "character": character},
+            "context": {"includeDeclaration": true},
+        }),
+    )
+}
+
+fn result(replies: &[Value], id: u64) -> &Value {
+    &replies.iter().find(|r| r["id"] == id).unwrap()["result"]
+}
+
+fn range(line: u64, start: u64, end: u64) -> Value {
+    json!({"start": {"line": line, "character": start}, "end": {"line": line, "character": end}})
+}
+
+#[test]
+fn session_lifecycle_and_diagnostics() {
+    let replies = exchange(&[
+        request(1, "initialize", json!({"capabilities": {}})),
+        notification("initialized", json!({})),
+        open("let x: int = 1;\nprintln(x"),
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": {"uri": URI, "version": 2},
+                "contentChanges": [{"text": "let x: int = 1;\nprintln(x);"}],
+            }),
+        ),
+        request(2, "textDocument/unknown", json!({})),
+        request(3, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+        request(4, "shutdown", Value::Null),
+    ]);
+
+    assert_eq!(result(&replies, 1)["capabilities"]["hoverProvider"], true);
+
+    let diagnostics: Vec<&Value> = replies
+        .iter()
+        .filter(|r| r["method"] == "textDocument/publishDiagnostics")
+        .map(|r| &r["params"]["diagnostics"])
+        .collect();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0][0]["severity"], 1);
+    assert_eq!(
+        diagnostics[0][0]["message"],
+        "expected `)` to close `(` opened at 2:8"
+    );
+    assert_eq!(diagnostics[1], &json!([]));
+
+    let unknown = replies.iter().find(|r| r["id"] == 2).unwrap();
+    assert_eq!(unknown["error"]["code"], -32601);
+    assert_eq!(result(&replies, 3), &Value::Null);
+    // Nothing is read after `exit`.
+    assert!(replies.iter().all(|r| r["id"] != 4));
+}
+
+#[test]
+fn navigation() {
+    let text = "let 名前: int = 1;\nlet y: float = 名前 * 2;\n名前 += 1;\nprintln(y);\n";
+    let replies = exchange(&[
+        request(1, "initialize", json!({"capabilities": {}})),
+        open(text),
+        at(2, "textDocument/definition", 2, 1),
+        at(3, "textDocument/references", 0, 5),
+        at(4, "textDocument/hover", 3, 8),
+        at(5, "textDocument/completion", 3, 0),
+        request(
+            6,
+            "textDocument/documentSymbol",
+            json!({"textDocument": {"uri": URI}}),
+        ),
+        at(7, "textDocument/hover", 3, 1),
+    ]);
+
+    assert_eq!(
+        result(&replies, 2),
+        &json!({"uri": URI, "range": range(0, 4, 6)})
+    );
+
+    let references: Vec<&Value> = result(&replies, 3)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| &l["range"])
+        .collect();
+    assert_eq!(
+        references,
+        vec![&range(0, 4, 6), &range(1, 15, 17), &range(2, 0, 2)]
+    );
+
+    assert_eq!(
+        result(&replies, 4)["contents"]["value"],
+        "```whiteye\nlet y: float\n```"
+    );
+    assert_eq!(
+        result(&replies, 7)["contents"]["value"],
+        "built-in function `println`"
+    );
+
+    let labels: Vec<(&str, u64)> = result(&replies, 5)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| {
+            (
+                item["label"].as_str().unwrap(),
+                item["kind"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    let machine = Machine::new();
+    let mut expected = vec![("名前", 6), ("y", 6)];
+    expected.extend(machine.function_names().into_iter().map(|name| (name, 3)));
+    expected.extend(CONSTANTS.iter().map(|(name, _)| (*name, 21)));
+    assert_eq!(labels, expected);
+
+    let symbols: Vec<(&str, &str)> = result(&replies, 6)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["name"].as_str().unwrap(), s["detail"].as_str().unwrap()))
+        .collect();
+    assert_eq!(symbols, vec![("名前", "int"), ("y", "float")]);
+}
+
+#[test]
+fn qualified_names_are_not_local_variables() {
+    let text = "import \"lib.wy\" as m;\nlet x: int = m.x;\nprintln(x + m.x);\n";
+    let replies = exchange(&[
+        request(1, "initialize", json!({"capabilities": {}})),
+        open(text),
+        at(2, "textDocument/references", 1, 4),
+        at(3, "textDocument/definition", 1, 14),
+        at(4, "textDocument/hover", 2, 13),
+    ]);
+
+    let references: Vec<&Value> = result(&replies, 2)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| &l["range"])
+        .collect();
+    assert_eq!(references, vec![&range(1, 4, 5), &range(2, 8, 9)]);
+    assert_eq!(result(&replies, 3), &Value::Null);
+    assert_eq!(result(&replies, 4), &Value::Null);
+}
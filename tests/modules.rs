@@ -0,0 +1,254 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use whiteye::builtin_functions::{self, MODULES};
+use whiteye::lint::{lint_source, LintConfig, Rule};
+use whiteye::machine::{Machine, MachineError};
+use whiteye::parser::{parse, parse_with_spans};
+use whiteye::sandbox::Capabilities;
+use whiteye::{Engine, Error, Value};
+
+/// Writes `files` into a fresh directory named after `test`, returning its path.
+fn project(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("whiteye-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (name, source) in files {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    root
+}
+
+/// Runs the file at `path` with `search_path`, returning what it printed and its error.
+fn run(path: &Path, search_path: Vec<PathBuf>) -> (String, Result<(), MachineError>) {
+    let printed = Rc::new(RefCell::new(String::new()));
+    let sink = printed.clone();
+    let mut machine = Machine::new();
+    machine.set_output_fn(move |text| sink.borrow_mut().push_str(text));
+    machine.set_script_path(path);
+    machine.set_module_path(search_path);
+    let source = fs::read_to_string(path).unwrap();
+    let result = parse_with_spans(&source)
+        .unwrap()
+        .into_iter()
+        .try_for_each(|(statement, span)| machine.run_at(statement, span));
+    let printed = printed.take();
+    (printed, result)
+}
+
+#[test]
+fn imports_resolve_relative_to_the_importing_file_and_run_once() {
+    let root = project(
+        "relative",
+        &[
+            (
+                "main.wy",
+                "import \"lib/shapes.wy\";\nimport \"lib/shapes.wy\" as s;\n\
+                 println(shapes.sides + s.sides);\n",
+            ),
+            (
+                "lib/shapes.wy",
+                "import \"units.wy\" as u;\npub let sides: int = 3 * u.scale;\nprintln(0);\n",
+            ),
+            ("lib/units.wy", "pub let scale: int = 2;\n"),
+        ],
+    );
+    let (printed, result) = run(&root.join("main.wy"), Vec::new());
+    result.unwrap();
+    assert_eq!(printed, "0\n12\n");
+}
+
+#[test]
+fn module_names_are_found_on_the_search_path() {
+    let root = project(
+        "search",
+        &[
+            ("app/main.wy", "import geometry as g;\nprintln(g.tau);\n"),
+            ("lib/geometry.wy", "pub let tau: float = 6.25;\n"),
+        ],
+    );
+    let (printed, result) = run(&root.join("app/main.wy"), vec![root.join("lib")]);
+    result.unwrap();
+    assert_eq!(printed, "6.25\n");
+
+    let (_, result) = run(&root.join("app/main.wy"), Vec::new());
+    let error = result.unwrap_err();
+    assert!(matches!(&error, MachineError::ModuleNotFound { module, .. } if module == "geometry"));
+}
+
+#[test]
+fn only_pub_declarations_are_exported() {
+    let root = project(
+        "exports",
+        &[
+            (
+                "main.wy",
+                "import \"config.wy\";\nprintln(config.limit);\nprintln(config.secret);\n",
+            ),
+            (
+                "config.wy",
+                "let secret: int = 7;\npub let limit: int = secret + 1;\n",
+            ),
+        ],
+    );
+    let (printed, result) = run(&root.join("main.wy"), Vec::new());
+    assert_eq!(printed, "8\n");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Module config does not export secret"
+    );
+}
+
+#[test]
+fn import_cycles_and_module_errors_name_the_files() {
+    let root = project(
+        "cycle",
+        &[
+            ("a.wy", "import \"b.wy\";\n"),
+            ("b.wy", "import \"a.wy\";\n"),
+            ("broken.wy", "import \"bad.wy\";\n"),
+            ("bad.wy", "let x: int = 1;\nprintln(y);\n"),
+        ],
+    );
+    let a = fs::canonicalize(root.join("a.wy")).unwrap();
+    let b = fs::canonicalize(root.join("b.wy")).unwrap();
+    let (_, result) = run(&a, Vec::new());
+    let error = result.unwrap_err();
+    // The cycle is reported from inside the module that closes it.
+    let inner = match error {
+        MachineError::InModule { path, error, .. } if path == b => error,
+        error => panic!("unexpected error {:?}", error),
+    };
+    assert_eq!(
+        inner.to_string(),
+        format!(
+            "Import cycle: {} -> {} -> {}",
+            a.display(),
+            b.display(),
+            a.display()
+        )
+    );
+
+    let bad = fs::canonicalize(root.join("bad.wy")).unwrap();
+    let (_, result) = run(&root.join("broken.wy"), Vec::new());
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        format!("In module {}:2:1: Variable Undefined: y", bad.display())
+    );
+}
+
+#[test]
+fn imports_need_file_system_access() {
+    let mut machine = Machine::new();
+    machine.set_capabilities(Capabilities::none());
+    let import = parse("import geometry;").unwrap().remove(0);
+    assert!(matches!(
+        machine.run(import),
+        Err(MachineError::ImportDisabled)
+    ));
+    // Built-in modules are not files.
+    let import = parse("import math;").unwrap().remove(0);
+    machine.run(import).unwrap();
+
+    let read = parse("let x: int = m.x;").unwrap().remove(0);
+    assert!(matches!(
+        machine.run(read),
+        Err(MachineError::ModuleUndefined(m)) if m == "m"
+    ));
+    assert_eq!(machine.variables.get("x"), None::<&Value>);
+}
+
+#[test]
+fn imports_are_linted() {
+    let source = "import \"lib.wy\" as lib;\npub let exported: int = lib.x;\nprintln(other.y);\n";
+    let warnings = lint_source(source, &LintConfig::default()).unwrap();
+    let found: Vec<(Rule, usize, usize, &str)> = warnings
+        .iter()
+        .map(|w| (w.rule, w.span.line, w.span.column, w.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [(
+            Rule::UseBeforeDeclare,
+            3,
+            9,
+            "module `other` is not imported"
+        )]
+    );
+}
+
+#[test]
+fn built_in_modules_offer_qualified_calls() {
+    let root = project(
+        "builtin",
+        &[
+            (
+                "main.wy",
+                "import math as m;\nimport string;\nimport io;\n\
+                 io.println(m.sqrt(4.0), m.PI > 3, string.upper(\"ok\"), m.abs(-2));\n\
+                 println(m.format(\"x\"));\n",
+            ),
+            ("shadow/main.wy", "import math;\nprintln(math.sqrt);\n"),
+            ("shadow/math.wy", "pub let sqrt: int = 2;\n"),
+        ],
+    );
+    let (printed, result) = run(&root.join("main.wy"), Vec::new());
+    assert_eq!(printed, "2.0 true OK 2\n");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Module m does not export format"
+    );
+
+    // A module file of the same name comes first.
+    let (printed, result) = run(&root.join("shadow/main.wy"), Vec::new());
+    result.unwrap();
+    assert_eq!(printed, "2\n");
+}
+
+#[test]
+fn built_in_modules_list_registered_functions() {
+    let machine = Machine::new();
+    let registered = machine.function_names();
+    for (name, functions) in MODULES {
+        let module = builtin_functions::module(name).unwrap();
+        assert_eq!(module.functions.len(), functions.len(), "{}", name);
+        for function in functions {
+            assert!(registered.contains(function), "{}.{}", name, function);
+        }
+    }
+}
+
+#[test]
+fn engines_resolve_imports_relative_to_the_file_they_run() {
+    let root = project(
+        "engine",
+        &[
+            (
+                "app/main.wy",
+                "import \"lib.wy\";\nlet x: int = lib.value;\n",
+            ),
+            ("app/lib.wy", "pub let value: int = 5;\n"),
+        ],
+    );
+    // Tests run in the crate's directory, not the script's.
+    assert_ne!(std::env::current_dir().unwrap(), root.join("app"));
+    let mut engine = Engine::new();
+    engine.run_file(root.join("app/main.wy")).unwrap();
+    assert_eq!(engine.get_var("x"), Some(&Value::Integer(5)));
+
+    // Without a file, imports are looked for in the current directory.
+    let error = Engine::new().run("import \"lib.wy\";").unwrap_err();
+    let cwd = std::env::current_dir().unwrap();
+    assert!(
+        matches!(
+            &error,
+            Error::Runtime(MachineError::ModuleNotFound { searched, .. })
+                if searched == &[cwd.join("lib.wy")]
+        ),
+        "{:?}",
+        error
+    );
+}
@@ -0,0 +1,79 @@
+use whiteye::lexer::{tokenize, TokenKind};
+use whiteye::parser::parse;
+
+fn number(source: &str) -> TokenKind {
+    let (mut tokens, _) = tokenize(source).unwrap();
+    assert_eq!(tokens.len(), 1, "{}", source);
+    tokens.remove(0).kind
+}
+
+#[test]
+fn radix_integers() {
+    assert_eq!(number("0xFF"), TokenKind::Integer(255));
+    assert_eq!(number("0Xff"), TokenKind::Integer(255));
+    assert_eq!(number("0o17"), TokenKind::Integer(15));
+    assert_eq!(number("0b1010"), TokenKind::Integer(10));
+    assert_eq!(
+        number("0x7FFF_FFFF_FFFF_FFFF"),
+        TokenKind::Integer(isize::MAX)
+    );
+}
+
+#[test]
+fn exponents() {
+    assert_eq!(number("1e10"), TokenKind::Float(1e10));
+    assert_eq!(number("1.5e-3"), TokenKind::Float(1.5e-3));
+    assert_eq!(number("2E+2"), TokenKind::Float(200.0));
+    // An exponent makes a float even without a point.
+    assert_eq!(number("3e0"), TokenKind::Float(3.0));
+}
+
+#[test]
+fn underscores_separate_digits() {
+    assert_eq!(number("1_000_000"), TokenKind::Integer(1_000_000));
+    assert_eq!(number("0b_1111_0000"), TokenKind::Integer(0xF0));
+    assert_eq!(number("1_000.000_5"), TokenKind::Float(1000.0005));
+    // A leading `_` starts an identifier instead.
+    assert_eq!(number("_1"), TokenKind::Identifier("_1".to_string()));
+}
+
+#[test]
+fn a_point_needs_digits_after_it() {
+    assert_eq!(number(".5"), TokenKind::Float(0.5));
+    let (tokens, _) = tokenize("5.").unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, vec![TokenKind::Integer(5), TokenKind::Dot]);
+    assert!(parse("println(5.);").is_err());
+}
+
+#[test]
+fn out_of_range_literals_are_positioned_errors() {
+    assert_eq!(
+        number("9223372036854775807"),
+        TokenKind::Integer(isize::MAX)
+    );
+    for (source, column, message) in [
+        (
+            "let x: int = 9223372036854775808;",
+            14,
+            "integer literal out of range",
+        ),
+        (
+            "let x: int = 1 + 0x1_0000_0000_0000_0000;",
+            18,
+            "integer literal out of range",
+        ),
+        ("let x: float = 1e400;", 16, "float literal out of range"),
+        ("let x: int = 0b;", 14, "expected digits after radix prefix"),
+    ] {
+        let errors = parse(source).unwrap_err();
+        assert_eq!(errors.len(), 1, "{}", source);
+        assert_eq!(
+            (errors[0].span.line, errors[0].span.column),
+            (1, column),
+            "{}",
+            source
+        );
+        assert_eq!(errors[0].message, message);
+    }
+}
@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::rc::Rc;
+
+use whiteye::machine::Machine;
+use whiteye::parser::parse;
+
+/// A `Write` whose contents stay readable after it is handed to the machine.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run(machine: &mut Machine, source: &str) {
+    for ast in parse(source).unwrap() {
+        machine.run(ast).unwrap();
+    }
+}
+
+#[test]
+fn print_writes_to_the_configured_sink() {
+    let buffer = SharedBuffer::default();
+    let mut machine = Machine::new();
+    machine.set_output(buffer.clone());
+
+    run(
+        &mut machine,
+        "let x: int = 3;\nprint(x, \"and\");\nprint(\" \");\nprintln(x * 1.5);\nprintln(x > 2);",
+    );
+    assert_eq!(
+        String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+        "3 and 4.5\ntrue\n"
+    );
+}
+
+#[test]
+fn print_can_be_routed_to_a_closure() {
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let sink = lines.clone();
+    let mut machine = Machine::new();
+    machine.set_output_fn(move |text| sink.borrow_mut().push(text.trim_end().to_string()));
+
+    run(&mut machine, "println(1);\nprintln(2);");
+    assert_eq!(*lines.borrow(), vec!["1", "2"]);
+}
+
+#[test]
+fn input_comes_from_the_configured_source() {
+    let mut machine = Machine::new();
+    machine.set_input(Cursor::new("first\r\nsecond"));
+
+    assert_eq!(machine.read_line().unwrap().as_deref(), Some("first"));
+    assert_eq!(machine.read_line().unwrap().as_deref(), Some("second"));
+    assert_eq!(machine.read_line().unwrap(), None);
+}
+
+#[test]
+fn scripts_read_input_write_errors_and_get_args() {
+    let output = SharedBuffer::default();
+    let errors = SharedBuffer::default();
+    let mut machine = Machine::new();
+    machine.set_output(output.clone());
+    machine.set_error_output(errors.clone());
+    machine.set_input(Cursor::new("Ada\n"));
+    machine.set_args(vec!["-v".to_string(), "3".to_string()]);
+
+    run(
+        &mut machine,
+        "let name: string = input(\"name: \");\neprint(\"hello\");\neprintln(\"\", name);\nprintln(args());",
+    );
+    assert_eq!(
+        String::from_utf8(output.0.borrow().clone()).unwrap(),
+        "name: [-v, 3]\n"
+    );
+    assert_eq!(
+        String::from_utf8(errors.0.borrow().clone()).unwrap(),
+        "hello Ada\n"
+    );
+
+    let ast = parse("println(input());").unwrap().remove(0);
+    assert_eq!(
+        machine.run(ast).unwrap_err().to_string(),
+        "Function input failed: end of input"
+    );
+}
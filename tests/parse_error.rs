@@ -0,0 +1,34 @@
+use whiteye::parser::parse;
+
+#[test]
+fn reports_every_statement_error() {
+    let errors = parse("let a: int = ;\nprintln(a);\nlet b: int = 1\nlet c: int = 2;").unwrap_err();
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "1:14: expected `(` or literal or identifier, found `;`",
+            "3:15: expected `;` after statement",
+        ]
+    );
+}
+
+#[test]
+fn unclosed_parenthesis_points_at_opening() {
+    let errors = parse("let x: int = 1;\nlet y: int = 2 * (x\n  + 1;").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    let e = &errors[0];
+    assert_eq!((e.span.line, e.span.column), (3, 6));
+    assert_eq!(e.expected, vec!["`)`"]);
+    assert_eq!(e.found, "`;`");
+    assert_eq!(e.message, "expected `)` to close `(` opened at 2:18");
+}
+
+#[test]
+fn lexer_and_parser_errors_are_combined() {
+    let errors = parse("let a: int = 0x;\nlet b: int = 1 $ 2;\nprintln(a b);").unwrap_err();
+    let lines: Vec<usize> = errors.iter().map(|e| e.span.line).collect();
+    assert_eq!(lines, vec![1, 2, 3]);
+    assert_eq!(errors[0].message, "expected digits after radix prefix");
+    assert_eq!(errors[1].message, "unexpected character `$`");
+}
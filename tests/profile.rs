@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use whiteye::machine::Machine;
+use whiteye::parser::parse_with_spans;
+use whiteye::profile::{Profiler, Tracer};
+
+/// Runs `source` `times` times over on one machine with `hook` installed.
+fn run(source: &str, times: usize, hook: impl whiteye::machine::DebugHook + 'static) {
+    let mut machine = Machine::new();
+    machine.set_output(std::io::sink());
+    machine.set_debug_hook(hook);
+    for _ in 0..times {
+        for (statement, span) in parse_with_spans(source).unwrap() {
+            machine.run_at(statement, span).unwrap();
+        }
+    }
+}
+
+#[test]
+fn profiler_counts_statements_and_calls() {
+    let profiler = Rc::new(RefCell::new(Profiler::new()));
+    run(
+        "let x: int = 1;\nprintln(x + x);\n  println(2);\n",
+        3,
+        profiler.clone(),
+    );
+    let profiler = profiler.borrow();
+
+    let mut statements: Vec<_> = profiler
+        .statements()
+        .into_iter()
+        .map(|s| (s.line, s.column, s.text, s.count))
+        .collect();
+    statements.sort();
+    assert_eq!(
+        statements,
+        [
+            (1, 1, "let x: int = 1;".to_string(), 3),
+            (2, 1, "println(x + x);".to_string(), 3),
+            (3, 3, "println(2);".to_string(), 3),
+        ]
+    );
+
+    let functions = profiler.functions();
+    assert_eq!(functions.len(), 1);
+    assert_eq!(
+        (functions[0].name.as_str(), functions[0].calls),
+        ("println", 6)
+    );
+    assert!(functions[0].total <= profiler.statements().iter().map(|s| s.total).sum());
+
+    let folded = profiler.folded();
+    let mut frames: Vec<&str> = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    frames.sort_unstable();
+    assert_eq!(
+        frames,
+        [
+            "main;1:1 let x: int = 1",
+            "main;2:1 println(x + x)",
+            "main;2:1 println(x + x);println",
+            "main;3:3 println(2)",
+            "main;3:3 println(2);println",
+        ]
+    );
+    assert!(profiler.report().contains("println(x + x);"));
+}
+
+#[test]
+fn tracer_shows_only_changed_variables() {
+    let tracer = Rc::new(RefCell::new(Tracer::new(Vec::new())));
+    run(
+        "let a: int = 1;\nlet b: bool = true;\nb = true;\na -= 2;\n",
+        1,
+        tracer.clone(),
+    );
+    let trace = String::from_utf8(tracer.borrow().output().clone()).unwrap();
+    assert_eq!(
+        trace,
+        "1:1 let a: int = 1;\n    a = 1\n\
+         2:1 let b: bool = true;\n    b = true\n\
+         3:1 b = true;\n\
+         4:1 a -= 2;\n    a = -1\n"
+    );
+}
@@ -0,0 +1,85 @@
+use whiteye::snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+use whiteye::{Engine, Error, Value};
+
+#[test]
+fn variables_survive_a_round_trip() {
+    let mut engine = Engine::new();
+    engine.set_var("name", "白目");
+    engine.set_var(
+        "values",
+        vec![Value::Integer(1), Value::Float(f64::INFINITY)],
+    );
+    engine
+        .run("let count: int = 3;\nlet ratio: float = count / 2.0;")
+        .unwrap();
+    let json = engine.snapshot().to_json();
+
+    let mut restored = Engine::new();
+    restored
+        .restore(Snapshot::from_json(&json).unwrap())
+        .unwrap();
+    assert_eq!(restored.eval("count * 2").unwrap(), Value::Integer(6));
+    assert_eq!(restored.get_var("ratio"), Some(&Value::Float(1.5)));
+    assert_eq!(restored.get_var("name"), engine.get_var("name"));
+    assert_eq!(restored.get_var("values"), engine.get_var("values"));
+}
+
+#[test]
+fn other_versions_and_formats_are_rejected() {
+    let json = Engine::new().snapshot().to_json();
+    let future = json.replace(
+        &format!("\"version\": {}", SNAPSHOT_VERSION),
+        &format!("\"version\": {}", SNAPSHOT_VERSION + 1),
+    );
+    assert!(matches!(
+        Snapshot::from_json(&future),
+        Err(SnapshotError::UnsupportedVersion { .. })
+    ));
+    assert!(matches!(
+        Snapshot::from_json("{\"variables\": {}}"),
+        Err(SnapshotError::NotASnapshot)
+    ));
+}
+
+#[test]
+fn restoring_needs_the_same_functions() {
+    let mut engine = Engine::new();
+    engine.register_fn("double", |x: isize| x * 2);
+    engine.set_var("x", 1);
+    let snapshot = engine.snapshot();
+
+    let mut restored = Engine::new();
+    let e = restored.restore(snapshot).unwrap_err();
+    assert!(
+        matches!(e, Error::Snapshot(SnapshotError::MissingFunction(ref name)) if name == "double")
+    );
+    assert_eq!(restored.get_var("x"), None);
+}
+
+#[test]
+fn imports_survive_a_round_trip() {
+    let root = std::env::temp_dir().join(format!("whiteye-snapshot-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.wy"), "pub let v: int = 7;\n").unwrap();
+    std::fs::write(
+        root.join("main.wy"),
+        "import \"a.wy\";\nimport math as m;\n",
+    )
+    .unwrap();
+    let mut engine = Engine::new();
+    engine.run_file(root.join("main.wy")).unwrap();
+    let json = engine.snapshot().to_json();
+
+    let mut restored = Engine::new();
+    restored
+        .restore(Snapshot::from_json(&json).unwrap())
+        .unwrap();
+    assert_eq!(restored.eval("a.v + 1").unwrap(), Value::Integer(8));
+    assert_eq!(restored.eval("m.abs(-2)").unwrap(), Value::Integer(2));
+
+    // The module is not run again, so changes to its file go unseen.
+    std::fs::write(root.join("a.wy"), "pub let v: int = 0;\n").unwrap();
+    restored.machine_mut().set_script_path(root.join("main.wy"));
+    restored.run("import \"a.wy\" as again;").unwrap();
+    assert_eq!(restored.eval("again.v").unwrap(), Value::Integer(7));
+}
@@ -0,0 +1,52 @@
+use whiteye::machine::Machine;
+use whiteye::parser::parse;
+use whiteye::value::Value;
+
+fn run(source: &str) -> Machine {
+    let mut machine = Machine::new();
+    for ast in parse(source).unwrap() {
+        machine.run(ast).unwrap();
+    }
+    machine
+}
+
+fn errors(source: &str) -> Vec<String> {
+    parse(source)
+        .unwrap_err()
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[test]
+fn statements_end_with_semicolons() {
+    assert_eq!(parse("let x: int = 1; x += 2;\n\n").unwrap().len(), 2);
+    assert_eq!(parse("let x: int = 1; // done\n").unwrap().len(), 1);
+    assert_eq!(parse("  \n").unwrap().len(), 0);
+}
+
+#[test]
+fn statements_may_span_lines() {
+    let machine = run("let x: int = (1 +\n  2)\n  * 3\n;\nx\n  +=\n  x;");
+    assert_eq!(machine.variables.get("x"), Some(&Value::Integer(18)));
+    assert_eq!(parse("println(\n  1,\n  2\n);").unwrap().len(), 1);
+}
+
+#[test]
+fn missing_semicolon_is_reported_after_the_statement() {
+    assert_eq!(
+        errors("let x: int = 1"),
+        vec!["1:15: expected `;` after statement"]
+    );
+    assert_eq!(
+        errors("let x: int = (1 +\n  2)\nx += 1;"),
+        vec!["2:5: expected `;` after statement"]
+    );
+    assert_eq!(
+        errors("test \"t\" {\n  let x: int = 1\n}"),
+        vec!["2:17: expected `;` after statement"]
+    );
+    let error = &parse("x = 1 x = 2;").unwrap_err()[0];
+    assert_eq!(error.expected, vec!["`;`"]);
+    assert_eq!(error.found, "identifier `x`");
+}
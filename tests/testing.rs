@@ -0,0 +1,96 @@
+use whiteye::formatter::format_source;
+use whiteye::lint::{lint_source, LintConfig, Rule};
+use whiteye::machine::{Machine, MachineError};
+use whiteye::parser::parse;
+use whiteye::testing::run_tests;
+use whiteye::Value;
+
+const SOURCE: &str = r#"let limit: int = 10;
+println(limit);
+
+test "within limit" {
+    let x: int = 4;
+    assert(x < limit);
+    assert_eq(x * 2, 8);
+}
+
+test "type mismatch" {
+    println(limit);
+    assert_eq(limit / 4, 2.5);
+}
+"#;
+
+#[test]
+fn tests_run_after_setup_and_report_failures() {
+    let results = run_tests(SOURCE).unwrap();
+    let names: Vec<(&str, bool)> = results
+        .iter()
+        .map(|r| (r.name.as_str(), r.passed()))
+        .collect();
+    assert_eq!(names, [("within limit", true), ("type mismatch", false)]);
+    assert_eq!(results[1].span.line, 10);
+
+    let failure = results[1].failure.as_ref().unwrap();
+    assert!(matches!(
+        &failure.error,
+        MachineError::AssertEqFailed {
+            left: Value::Integer(2),
+            right: Value::Float(_),
+        }
+    ));
+    assert_eq!((failure.location.line, failure.location.column), (12, 5));
+    assert_eq!(failure.output, "10\n10\n");
+    assert_eq!(
+        failure.render("limits.wy", SOURCE),
+        "limits.wy:12:5: Assertion failed: left != right\n  \
+         left:  2 (int)\n  \
+         right: 2.5 (float)\n    \
+         assert_eq(limit / 4, 2.5);\n    \
+         ^\n\
+         output:\n10\n10\n"
+    );
+}
+
+#[test]
+fn test_blocks_are_skipped_by_normal_runs() {
+    let mut machine = Machine::new();
+    machine.set_output(std::io::sink());
+    for statement in parse(SOURCE).unwrap() {
+        machine.run(statement).unwrap();
+    }
+    assert!(!machine.variables.contains_key("x"));
+
+    let failing = parse("assert(false, 3);").unwrap().remove(0);
+    let error = machine.run(failing).unwrap_err();
+    assert_eq!(error.to_string(), "Assertion failed: 3");
+
+    let wrong_type = parse("assert(1);").unwrap().remove(0);
+    assert!(matches!(
+        machine.run(wrong_type),
+        Err(MachineError::ArgumentType { position: 1, .. })
+    ));
+}
+
+#[test]
+fn test_blocks_are_parsed_formatted_and_linted() {
+    let errors = parse("test \"outer\" { test \"inner\" { } }").unwrap_err();
+    assert_eq!(errors[0].message, "test blocks cannot be nested");
+    let errors = parse("test \"open\" { assert(true);").unwrap_err();
+    assert_eq!(errors[0].message, "unclosed `{` of test block");
+    let errors = parse("test \"bad \\q\" {}").unwrap_err();
+    assert_eq!(errors[0].message, "unknown escape sequence");
+
+    // `test` is only a keyword in front of a name.
+    assert!(parse("let test: int = 1; test += 1;").is_ok());
+
+    let source = "test \"a \\\"quoted\\\" name\"{let y:int=1; // one\nassert(y==1);}\n";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "test \"a \\\"quoted\\\" name\" {\n    let y: int = 1; // one\n    assert(y == 1);\n}\n"
+    );
+
+    let source = "let used: int = 1;\ntest \"t\" {\n    let unused: int = used;\n}\n";
+    let warnings = lint_source(source, &LintConfig::default()).unwrap();
+    let found: Vec<(Rule, usize)> = warnings.iter().map(|w| (w.rule, w.span.line)).collect();
+    assert_eq!(found, [(Rule::UnusedVariable, 3)]);
+}
@@ -0,0 +1,54 @@
+use whiteye::lexer::{tokenize, TokenKind};
+use whiteye::machine::Machine;
+use whiteye::parser::parse;
+use whiteye::value::Value;
+
+fn run(source: &str) -> Machine {
+    let mut machine = Machine::new();
+    for ast in parse(source).unwrap() {
+        machine.run(ast).unwrap();
+    }
+    machine
+}
+
+#[test]
+fn japanese_identifier() {
+    let machine = run("let 合計: int = 0;\n合計 += 5;");
+    assert_eq!(machine.variables.get("合計"), Some(&Value::Integer(5)));
+}
+
+#[test]
+fn identifiers_are_nfc_normalized() {
+    // "e" followed by a combining acute accent, then the precomposed "é".
+    let machine = run("let caf\u{65}\u{301}: int = 1;\ncaf\u{e9} += 1;");
+    assert_eq!(machine.variables.len(), 1);
+    assert_eq!(machine.variables.get("caf\u{e9}"), Some(&Value::Integer(2)));
+}
+
+#[test]
+fn full_width_digits_continue_identifiers() {
+    let machine = run("let 値１: int = 1;\nlet 値1: int = 2;");
+    assert_eq!(machine.variables.get("値１"), Some(&Value::Integer(1)));
+    assert_eq!(machine.variables.get("値1"), Some(&Value::Integer(2)));
+}
+
+#[test]
+fn full_width_digits_do_not_start_identifiers_or_numbers() {
+    assert!(tokenize("１値").is_err());
+    assert!(parse("let x: int = １２;").is_err());
+}
+
+#[test]
+fn keywords_are_not_identifiers() {
+    let (tokens, _) = tokenize("let while_ true").unwrap();
+    let kinds: Vec<TokenKind> = tokens.into_iter().map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Let,
+            TokenKind::Identifier("while_".to_string()),
+            TokenKind::True
+        ]
+    );
+    assert!(parse("let while: int = 1;").is_err());
+}